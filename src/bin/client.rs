@@ -1,18 +1,32 @@
+use lan_shootmans::breakable::breakable_plugin;
 use lan_shootmans::cursor::cursor_plugin;
+use lan_shootmans::damage_feedback::damage_feedback_plugin;
+use lan_shootmans::hazard::hazard_plugin;
 use lan_shootmans::health::health_plugin;
+use lan_shootmans::hitbox::hitbox_plugin;
+use lan_shootmans::input::input_plugin;
 use lan_shootmans::lifetime::lifetime_plugin;
+use lan_shootmans::pickup::pickup_plugin;
+use lan_shootmans::respawn::respawn_plugin;
+use lan_shootmans::spectate::spectate_plugin;
+use bevy::log::LogPlugin;
 use bevy::pbr::wireframe::WireframePlugin;
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use bevy_trenchbroom::prelude::*;
-use lan_shootmans::dev_console::developer_console_plugin;
+use lan_shootmans::dev_console::{console_log_layer, developer_console_plugin};
+use lan_shootmans::dev_console_ui::developer_console_ui_plugin;
 use lan_shootmans::trenchbroom;
 use lan_shootmans::player;
+use lan_shootmans::weapon::weapon_plugin;
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins.set(ImagePlugin {
             default_sampler: repeating_image_sampler(false),
+        }).set(LogPlugin {
+            custom_layer: console_log_layer,
+            ..default()
         }))
         .add_plugins(bevy_egui::EguiPlugin)
         .add_plugins(TrenchBroomPlugin::new(trenchbroom::trenchbroom_config()))
@@ -21,11 +35,20 @@ fn main() {
         .add_plugins(WireframePlugin)
         .add_plugins(lan_shootmans::netcode::client_plugin)
         .add_plugins(developer_console_plugin)
+        .add_plugins(developer_console_ui_plugin)
+        .add_plugins(input_plugin)
         .add_plugins(health_plugin)
+        .add_plugins(damage_feedback_plugin)
+        .add_plugins(hitbox_plugin)
+        .add_plugins(hazard_plugin)
+        .add_plugins(breakable_plugin)
         .add_plugins(lifetime_plugin)
+        .add_plugins(pickup_plugin)
         .add_plugins(cursor_plugin)
-        //.add_plugins(flycam::flycam_plugin)
         .add_plugins(player::first_person_controller_plugin)
+        .add_plugins(respawn_plugin)
+        .add_plugins(spectate_plugin)
+        .add_plugins(weapon_plugin)
         .add_systems(Startup, spawn_test_map)
         .run();
 }
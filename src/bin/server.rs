@@ -1,10 +1,35 @@
 use bevy::log::LogPlugin;
 use bevy::prelude::*;
+use lan_shootmans::damage_modifiers::damage_modifiers_console_plugin;
+use lan_shootmans::dev_console::{console_log_layer, developer_console_plugin, stdin_console_plugin};
+use lan_shootmans::explosion::explosion_console_plugin;
+use lan_shootmans::health::health_console_plugin;
+use lan_shootmans::inventory::inventory_console_plugin;
+use lan_shootmans::player::player_console_plugin;
+use lan_shootmans::respawn::respawn_console_plugin;
+use lan_shootmans::spectate::spectate_console_plugin;
+use lan_shootmans::weapon::weapon_definitions_plugin;
 
 fn main() {
     App::new()
         .add_plugins(MinimalPlugins)
-        .add_plugins(LogPlugin::default())
+        .add_plugins(LogPlugin {
+            custom_layer: console_log_layer,
+            ..default()
+        })
+        .add_plugins(AssetPlugin::default())
+        .add_plugins(developer_console_plugin)
+        .add_plugins(stdin_console_plugin)
+        // Shots are checked against the weapons they were fired with
+        .add_plugins(weapon_definitions_plugin)
+        // Only the cvars and commands of the gameplay plugins, the server doesn't simulate any of it yet
+        .add_plugins(health_console_plugin)
+        .add_plugins(damage_modifiers_console_plugin)
+        .add_plugins(explosion_console_plugin)
+        .add_plugins(respawn_console_plugin)
+        .add_plugins(spectate_console_plugin)
+        .add_plugins(inventory_console_plugin)
+        .add_plugins(player_console_plugin)
         .add_plugins(lan_shootmans::netcode::server_plugin)
         .run();
-}
\ No newline at end of file
+}
@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy_rapier3d::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::explosion::ExplosionEvent;
+use crate::health::{DamageSource, DeathEvent, Health};
+use crate::inventory::{weapon_path, MakeEntityWeaponPickup};
+use crate::lifetime::Lifetime;
+use crate::netcode::{ClientMessage, ServerMessage};
+
+const PROP_SIZE: f32 = 0.8;
+const DEBRIS_SIZE: f32 = 0.15;
+const DEBRIS_SPEED: f32 = 4.0;
+const DEBRIS_LIFETIME: f32 = 5.0;
+
+/// Blows up when whatever it's on breaks, like an explosive barrel
+#[derive(Clone, Copy)]
+pub struct BreakExplosion {
+    pub damage: i32,
+    pub radius: f32,
+    pub knockback: f32,
+}
+
+/// Something in the map that shatters once its `Health` runs out
+#[derive(Component)]
+pub struct Breakable {
+    /// Handed out in the order the map spawns its entities, so every client loading the same map agrees on it
+    id: u32,
+    /// Chunks of debris left flying when it breaks
+    debris: u32,
+    explosion: Option<BreakExplosion>,
+    /// Weapon left behind once it's broken, as in assets/weapons/<weapon>.weapon.ron
+    drop_weapon: Option<String>,
+}
+
+#[derive(Resource, Default)]
+struct NextBreakableId(u32);
+
+/// Triggered on a breakable to shatter it, `broken_by` is only set when it was broken on this client
+#[derive(Event)]
+pub struct BreakEvent {
+    pub broken_by: Option<Entity>,
+}
+
+#[derive(Resource)]
+pub struct BreakableAssets {
+    prop_mesh: Handle<Mesh>,
+    prop_material: Handle<StandardMaterial>,
+    explosive_material: Handle<StandardMaterial>,
+    debris_mesh: Handle<Mesh>,
+    debris_material: Handle<StandardMaterial>,
+}
+
+fn setup_breakable_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(BreakableAssets {
+        prop_mesh: meshes.add(Cuboid::new(PROP_SIZE, PROP_SIZE, PROP_SIZE)),
+        prop_material: materials.add(Color::srgb(0.6, 0.4, 0.2)),
+        explosive_material: materials.add(Color::srgb(0.8, 0.1, 0.1)),
+        debris_mesh: meshes.add(Cuboid::new(DEBRIS_SIZE, DEBRIS_SIZE, DEBRIS_SIZE)),
+        debris_material: materials.add(Color::srgb(0.4, 0.3, 0.2)),
+    });
+}
+
+pub trait MakeEntityBreakable {
+    /// For brush entities, which already have their meshes and collider
+    fn make_breakable(&mut self, health: i32, debris: u32, explosion: Option<BreakExplosion>, drop_weapon: Option<String>) -> &mut Self;
+    /// For point entities, which get a crate to go with it
+    fn make_breakable_prop(&mut self, transform: Transform, health: i32, debris: u32, explosion: Option<BreakExplosion>, drop_weapon: Option<String>) -> &mut Self;
+}
+
+impl MakeEntityBreakable for EntityWorldMut<'_> {
+    fn make_breakable(&mut self, health: i32, debris: u32, explosion: Option<BreakExplosion>, drop_weapon: Option<String>) -> &mut Self {
+        let id = self.world_scope(|world| {
+            let mut next_id = world.get_resource_or_insert_with(NextBreakableId::default);
+            next_id.0 += 1;
+            next_id.0
+        });
+
+        self.insert((
+            Breakable { id, debris, explosion, drop_weapon },
+            Health::with_max(health),
+        ))
+    }
+
+    fn make_breakable_prop(&mut self, transform: Transform, health: i32, debris: u32, explosion: Option<BreakExplosion>, drop_weapon: Option<String>) -> &mut Self {
+        let assets = self.world().resource::<BreakableAssets>();
+        let material = if explosion.is_some() { &assets.explosive_material } else { &assets.prop_material };
+        let (mesh, material) = (assets.prop_mesh.clone(), material.clone());
+
+        self.insert((
+            PbrBundle {
+                mesh,
+                material,
+                transform,
+                ..default()
+            },
+            Collider::cuboid(PROP_SIZE / 2.0, PROP_SIZE / 2.0, PROP_SIZE / 2.0),
+        ))
+        .make_breakable(health, debris, explosion, drop_weapon)
+    }
+}
+
+// Brush entities sit at the map origin with their meshes built around wherever they really are,
+// so this goes by the bounds of the meshes rather than the transform
+fn breakable_center(
+    entity: Entity,
+    transforms: &Query<&GlobalTransform>,
+    children: &Query<&Children>,
+    bounds: &Query<(&Aabb, &GlobalTransform)>,
+) -> Vec3 {
+    let centers: Vec<Vec3> = std::iter::once(entity)
+        .chain(children.get(entity).into_iter().flatten().copied())
+        .filter_map(|entity| bounds.get(entity).ok())
+        .map(|(aabb, transform)| transform.transform_point(aabb.center.into()))
+        .collect();
+
+    if centers.is_empty() {
+        transforms.get(entity).map_or(Vec3::ZERO, GlobalTransform::translation)
+    } else {
+        centers.iter().sum::<Vec3>() / centers.len() as f32
+    }
+}
+
+fn break_on_death(
+    trigger: Trigger<DeathEvent>,
+    breakables: Query<&Breakable>,
+    mut messages: EventWriter<ClientMessage>,
+    mut commands: Commands,
+) {
+    let Ok(breakable) = breakables.get(trigger.entity()) else {
+        return;
+    };
+
+    messages.send(ClientMessage::PropBroken { id: breakable.id });
+    commands.trigger_targets(BreakEvent { broken_by: Some(trigger.event().killing_blow.originating_entity) }, trigger.entity());
+}
+
+fn receive_broken_props(
+    mut messages: EventReader<ServerMessage>,
+    breakables: Query<(Entity, &Breakable)>,
+    mut commands: Commands,
+) {
+    for message in messages.read() {
+        let ServerMessage::PropBroken { id, .. } = message else {
+            continue;
+        };
+        // Already gone if it was broken here as well
+        if let Some((entity, _)) = breakables.iter().find(|(_, breakable)| breakable.id == *id) {
+            commands.trigger_targets(BreakEvent { broken_by: None }, entity);
+        }
+    }
+}
+
+fn shatter_breakables(
+    trigger: Trigger<BreakEvent>,
+    breakables: Query<&Breakable>,
+    transforms: Query<&GlobalTransform>,
+    children: Query<&Children>,
+    bounds: Query<(&Aabb, &GlobalTransform)>,
+    assets: Res<BreakableAssets>,
+    mut commands: Commands,
+) {
+    let entity = trigger.entity();
+    let Ok(breakable) = breakables.get(entity) else {
+        return;
+    };
+    let center = breakable_center(entity, &transforms, &children, &bounds);
+    debug!("Breakable {} broke at {center}", breakable.id);
+
+    // Whoever broke it does the damage, the explosion is already happening on their end for everyone else
+    if let (Some(explosion), Some(broken_by)) = (breakable.explosion, trigger.event().broken_by) {
+        commands.trigger(ExplosionEvent {
+            originating_entity: broken_by,
+            source: DamageSource::Other("breakable".into()),
+            position: center,
+            radius: explosion.radius,
+            damage: explosion.damage,
+            knockback: explosion.knockback,
+        });
+    }
+
+    // Seeded by the breakable, so the debris flies the same way on every client
+    let mut rng = StdRng::seed_from_u64(breakable.id as u64);
+    for _ in 0..breakable.debris {
+        let direction = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(0.0..1.0), rng.gen_range(-1.0..1.0))
+            .normalize_or_zero();
+        commands.spawn((
+            PbrBundle {
+                mesh: assets.debris_mesh.clone(),
+                material: assets.debris_material.clone(),
+                transform: Transform::from_translation(center + direction * DEBRIS_SIZE),
+                ..default()
+            },
+            RigidBody::Dynamic,
+            Collider::cuboid(DEBRIS_SIZE / 2.0, DEBRIS_SIZE / 2.0, DEBRIS_SIZE / 2.0),
+            Velocity::linear(direction * DEBRIS_SPEED * rng.gen_range(0.5..1.0)),
+            Lifetime(Timer::from_seconds(DEBRIS_LIFETIME, TimerMode::Once)),
+        ));
+    }
+
+    if let Some(weapon) = breakable.drop_weapon.clone() {
+        commands.spawn_empty().add(move |mut entity: EntityWorldMut| {
+            let definition = entity.world().resource::<AssetServer>().load(weapon_path(&weapon));
+            entity.make_weapon_pickup(Transform::from_translation(center), definition, None, None);
+        });
+    }
+
+    commands.entity(entity).despawn_recursive();
+}
+
+pub fn breakable_plugin(app: &mut App) {
+    app.add_event::<BreakEvent>()
+        .init_resource::<NextBreakableId>()
+        .add_systems(Startup, setup_breakable_assets)
+        .add_systems(Update, receive_broken_props)
+        .observe(break_on_death)
+        .observe(shatter_breakables);
+}
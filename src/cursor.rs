@@ -1,8 +1,8 @@
 use bevy::app::App;
-use bevy::input::ButtonInput;
 use bevy::log::error;
-use bevy::prelude::{Deref, DerefMut, KeyCode, PreUpdate, Query, Res, ResMut, Resource, Update, Window, With};
+use bevy::prelude::{Deref, DerefMut, Query, ResMut, Resource, Update, Window, With};
 use bevy::window::{CursorGrabMode, PrimaryWindow};
+use crate::input::{ActionInput, InputAction};
 
 // Want to avoid grabbing the whole window whenever we wanna make sure the cursor is grabbed
 #[derive(Resource, Deref, DerefMut, Default)]
@@ -22,7 +22,7 @@ pub fn toggle_grab_cursor(window: &mut Window) {
 }
 
 pub fn grab_cursor(
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: ActionInput,
     mut cursor_state: ResMut<CursorState>,
     mut primary_window: Query<&mut Window, With<PrimaryWindow>>,
 ) {
@@ -31,7 +31,7 @@ pub fn grab_cursor(
         return;
     };
     
-    if keys.just_pressed(KeyCode::Escape) {
+    if actions.just_pressed(InputAction::ToggleCursor) {
         toggle_grab_cursor(&mut window);
     }
     **cursor_state = window.cursor.grab_mode;
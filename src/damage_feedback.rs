@@ -0,0 +1,239 @@
+use bevy::prelude::*;
+use crate::health::{DamageEvent, KillEvent};
+use crate::player::{Player, PlayerCamera};
+
+/// How long a damage direction indicator stays on screen
+const INDICATOR_TIME: f32 = 1.5;
+/// How far out from the middle of the screen indicators sit, in percent of the screen
+const INDICATOR_DISTANCE: f32 = 15.0;
+const INDICATOR_SIZE: f32 = 16.0;
+/// Strongest the red screen flash gets, however much damage was taken
+const MAX_FLASH_ALPHA: f32 = 0.4;
+/// How quickly the screen flash fades out, in alpha per second
+const FLASH_FADE_RATE: f32 = 1.0;
+const HIT_MARKER_TIME: f32 = 0.25;
+const KILL_MARKER_TIME: f32 = 0.6;
+
+const HIT_COLOR: Color = Color::WHITE;
+const HEADSHOT_COLOR: Color = Color::srgb(1., 0.85, 0.);
+const KILL_COLOR: Color = Color::srgb(1., 0., 0.);
+const INDICATOR_COLOR: Color = Color::srgb(1., 0.3, 0.1);
+
+/// Points towards wherever some damage we took came from
+#[derive(Component)]
+struct DamageIndicator {
+    source: Vec3,
+    timer: Timer,
+}
+
+/// Red overlay over the whole screen when getting hurt
+#[derive(Component)]
+struct DamageFlash;
+
+/// Shows up over the crosshair when we hurt someone else
+#[derive(Component)]
+struct HitMarker {
+    timer: Timer,
+}
+
+#[derive(Resource)]
+struct FeedbackSounds {
+    hurt: Handle<AudioSource>,
+    hit: Handle<AudioSource>,
+    headshot: Handle<AudioSource>,
+    kill: Handle<AudioSource>,
+}
+
+fn setup_damage_feedback(asset_server: Res<AssetServer>, mut commands: Commands) {
+    commands.insert_resource(FeedbackSounds {
+        hurt: asset_server.load("sounds/hurt.wav"),
+        hit: asset_server.load("sounds/hit.wav"),
+        headshot: asset_server.load("sounds/headshot.wav"),
+        kill: asset_server.load("sounds/kill.wav"),
+    });
+
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            ..default()
+        },
+        background_color: Color::srgba(1., 0., 0., 0.).into(),
+        ..default()
+    }).insert(DamageFlash);
+
+    // Centred over the crosshair by the full screen container around it
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    }).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            "x",
+            TextStyle {
+                font_size: 40.,
+                color: HIT_COLOR,
+                ..default()
+            },
+        ))
+        .insert(Visibility::Hidden)
+        .insert(HitMarker { timer: Timer::from_seconds(HIT_MARKER_TIME, TimerMode::Once) });
+    });
+}
+
+fn play_sound(commands: &mut Commands, sound: &Handle<AudioSource>) {
+    commands.spawn(AudioBundle {
+        source: sound.clone(),
+        settings: PlaybackSettings::DESPAWN,
+    });
+}
+
+fn show_damage_taken(
+    trigger: Trigger<DamageEvent>,
+    players: Query<(), With<Player>>,
+    mut flashes: Query<&mut BackgroundColor, With<DamageFlash>>,
+    sounds: Res<FeedbackSounds>,
+    mut commands: Commands,
+) {
+    let victim = trigger.entity();
+    if !players.contains(victim) {
+        return;
+    }
+    let event = trigger.event();
+
+    // A bigger hit flashes harder, stacking on whatever is still fading out
+    for mut flash in flashes.iter_mut() {
+        let alpha = (flash.0.alpha() + event.damage as f32 / 100.0).min(MAX_FLASH_ALPHA);
+        flash.0.set_alpha(alpha);
+    }
+    play_sound(&mut commands, &sounds.hurt);
+
+    // Hurting ourselves or falling doesn't come from anywhere worth pointing at
+    let Some(source) = event.origin.filter(|_| event.originating_entity != victim) else {
+        return;
+    };
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Px(INDICATOR_SIZE),
+            height: Val::Px(INDICATOR_SIZE),
+            margin: UiRect {
+                left: Val::Px(-INDICATOR_SIZE / 2.),
+                top: Val::Px(-INDICATOR_SIZE / 2.),
+                ..default()
+            },
+            ..default()
+        },
+        background_color: INDICATOR_COLOR.into(),
+        ..default()
+    }).insert(DamageIndicator {
+        source,
+        timer: Timer::from_seconds(INDICATOR_TIME, TimerMode::Once),
+    });
+}
+
+fn show_hit_markers(
+    trigger: Trigger<DamageEvent>,
+    players: Query<(), With<Player>>,
+    mut markers: Query<(&mut HitMarker, &mut Text, &mut Visibility)>,
+    sounds: Res<FeedbackSounds>,
+    mut commands: Commands,
+) {
+    let event = trigger.event();
+    if !players.contains(event.originating_entity) || event.originating_entity == trigger.entity() {
+        return;
+    }
+
+    let (color, sound) = if event.is_headshot() {
+        (HEADSHOT_COLOR, &sounds.headshot)
+    } else {
+        (HIT_COLOR, &sounds.hit)
+    };
+    for (mut marker, mut text, mut visibility) in markers.iter_mut() {
+        marker.timer = Timer::from_seconds(HIT_MARKER_TIME, TimerMode::Once);
+        text.sections[0].style.color = color;
+        *visibility = Visibility::Inherited;
+    }
+    play_sound(&mut commands, sound);
+}
+
+// Kills are only known once the damage has gone through, so they override the hit marker afterwards
+fn show_kill_markers(
+    mut kills: EventReader<KillEvent>,
+    players: Query<(), With<Player>>,
+    mut markers: Query<(&mut HitMarker, &mut Text, &mut Visibility)>,
+    sounds: Res<FeedbackSounds>,
+    mut commands: Commands,
+) {
+    for kill in kills.read() {
+        if !players.contains(kill.victim) || !kill.killer.is_some_and(|killer| players.contains(killer) && killer != kill.victim) {
+            continue;
+        }
+
+        for (mut marker, mut text, mut visibility) in markers.iter_mut() {
+            marker.timer = Timer::from_seconds(KILL_MARKER_TIME, TimerMode::Once);
+            text.sections[0].style.color = KILL_COLOR;
+            *visibility = Visibility::Inherited;
+        }
+        play_sound(&mut commands, &sounds.kill);
+    }
+}
+
+fn update_damage_indicators(
+    mut indicators: Query<(Entity, &mut DamageIndicator, &mut Style, &mut BackgroundColor)>,
+    cameras: Query<(&GlobalTransform, &Camera), With<PlayerCamera>>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let camera_transform = cameras.iter()
+        .find(|(_, camera)| camera.is_active)
+        .map(|(transform, _)| transform);
+
+    for (entity, mut indicator, mut style, mut color) in indicators.iter_mut() {
+        if indicator.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        color.0.set_alpha(indicator.timer.fraction_remaining());
+
+        let Some(camera_transform) = camera_transform else {
+            continue;
+        };
+        // Flattened onto the screen, straight ahead being the top and behind being the bottom
+        let local = camera_transform.affine().inverse().transform_point3(indicator.source);
+        let direction = Vec2::new(local.x, -local.z).normalize_or_zero();
+        style.left = Val::Percent(50. + direction.x * INDICATOR_DISTANCE);
+        style.top = Val::Percent(50. - direction.y * INDICATOR_DISTANCE);
+    }
+}
+
+fn fade_damage_flash(mut flashes: Query<&mut BackgroundColor, With<DamageFlash>>, time: Res<Time>) {
+    for mut flash in flashes.iter_mut() {
+        let alpha = flash.0.alpha();
+        if alpha > 0.0 {
+            flash.0.set_alpha((alpha - FLASH_FADE_RATE * time.delta_seconds()).max(0.0));
+        }
+    }
+}
+
+fn fade_hit_markers(mut markers: Query<(&mut HitMarker, &mut Visibility)>, time: Res<Time>) {
+    for (mut marker, mut visibility) in markers.iter_mut() {
+        if marker.timer.tick(time.delta()).just_finished() {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
+pub fn damage_feedback_plugin(app: &mut App) {
+    app.add_systems(Startup, setup_damage_feedback)
+        .add_systems(Update, (show_kill_markers, update_damage_indicators, fade_damage_flash, fade_hit_markers))
+        .observe(show_damage_taken)
+        .observe(show_hit_markers);
+}
@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use crate::dev_console::{AddConsoleCommand, AddConsoleVariable, ConsoleCommandResult, DeveloperConsole};
+use crate::health::{DamageModification, DamageSource, DamageType, ModifyDamageEvent};
+use crate::player::{Player, PlayerCamera};
+use crate::respawn::{Dead, SpawnProtection};
+use crate::weapon::WeaponDefinition;
+
+/// Players on the same team only hurt each other with friendly fire on
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Team(pub u8);
+
+/// Takes no damage at all
+#[derive(Component)]
+pub struct GodMode;
+
+/// Takes damage as usual, but never drops below 1 health
+#[derive(Component)]
+pub struct Buddha;
+
+/// Multiplier on incoming damage of each type, types that aren't listed are taken in full
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct Resistances(pub HashMap<DamageType, f32>);
+
+fn friendly_fire_modifier(
+    trigger: Trigger<ModifyDamageEvent>,
+    teams: Query<&Team>,
+    console: Res<DeveloperConsole>,
+    mut modification: ResMut<DamageModification>,
+) {
+    let attacker = trigger.event().originating_entity;
+    // Hurting yourself isn't friendly fire, explosions already scale that down
+    if attacker == trigger.entity() || console.get_value::<bool>("mp_friendlyfire").unwrap_or(false) {
+        return;
+    }
+
+    if let (Ok(attacker_team), Ok(victim_team)) = (teams.get(attacker), teams.get(trigger.entity())) {
+        if attacker_team == victim_team {
+            modification.cancel();
+        }
+    }
+}
+
+// Picked up whenever a player spawns, so changing cl_team takes effect on the next respawn.
+// cl_team is our own choice, so it only goes on the player we control, the one with a camera
+fn assign_player_teams(
+    spawned: Query<Entity, Added<Player>>,
+    mut respawned: RemovedComponents<Dead>,
+    player_children: Query<&Children, With<Player>>,
+    cameras: Query<(), With<PlayerCamera>>,
+    console: Res<DeveloperConsole>,
+    mut commands: Commands,
+) {
+    let team = console.get_value::<u8>("cl_team").unwrap_or(0);
+    let is_local_player = |entity: Entity| player_children.get(entity)
+        .is_ok_and(|children| children.iter().any(|&child| cameras.contains(child)));
+
+    for player in spawned.iter().chain(respawned.read()).filter(|&entity| is_local_player(entity)) {
+        // Team 0 is on nobody's side, free to hurt and be hurt by anyone
+        if team == 0 {
+            commands.entity(player).remove::<Team>();
+        } else {
+            commands.entity(player).insert(Team(team));
+        }
+    }
+}
+
+fn spawn_protection_modifier(
+    trigger: Trigger<ModifyDamageEvent>,
+    protected: Query<(), With<SpawnProtection>>,
+    mut modification: ResMut<DamageModification>,
+) {
+    if protected.contains(trigger.entity()) {
+        modification.cancel();
+    }
+}
+
+fn god_mode_modifier(
+    trigger: Trigger<ModifyDamageEvent>,
+    gods: Query<(), With<GodMode>>,
+    mut modification: ResMut<DamageModification>,
+) {
+    if gods.contains(trigger.entity()) {
+        modification.cancel();
+    }
+}
+
+fn resistance_modifier(
+    trigger: Trigger<ModifyDamageEvent>,
+    resistances: Query<&Resistances>,
+    mut modification: ResMut<DamageModification>,
+) {
+    let Ok(resistances) = resistances.get(trigger.entity()) else {
+        return;
+    };
+    if let Some(&multiplier) = resistances.get(&trigger.event().damage_type) {
+        modification.scale(multiplier);
+    }
+}
+
+// Bullets hitting a particular hitbox region do whatever the weapon that fired them says they should
+fn hitbox_region_modifier(
+    trigger: Trigger<ModifyDamageEvent>,
+    weapon_definitions: Option<Res<Assets<WeaponDefinition>>>,
+    mut modification: ResMut<DamageModification>,
+) {
+    let event = trigger.event();
+    let (DamageType::Bullet, Some(region), DamageSource::Weapon(weapon)) = (event.damage_type, event.hit_region, &event.source) else {
+        return;
+    };
+    let Some(definition) = weapon_definitions.as_ref().and_then(|definitions| definitions.get(*weapon)) else {
+        return;
+    };
+
+    modification.scale(definition.hitbox_multipliers.get(region));
+}
+
+fn god_command(
+    In(_): In<Vec<String>>,
+    player_query: Query<(Entity, Has<GodMode>), With<Player>>,
+    mut commands: Commands,
+) -> ConsoleCommandResult {
+    let (player, god_mode) = player_query.get_single().map_err(|_| "No player to make a god".to_string())?;
+
+    if god_mode {
+        commands.entity(player).remove::<GodMode>();
+        Ok("godmode OFF".into())
+    } else {
+        commands.entity(player).insert(GodMode);
+        Ok("godmode ON".into())
+    }
+}
+
+fn buddha_command(
+    In(_): In<Vec<String>>,
+    player_query: Query<(Entity, Has<Buddha>), With<Player>>,
+    mut commands: Commands,
+) -> ConsoleCommandResult {
+    let (player, buddha) = player_query.get_single().map_err(|_| "No player to make a buddha".to_string())?;
+
+    if buddha {
+        commands.entity(player).remove::<Buddha>();
+        Ok("buddha mode OFF".into())
+    } else {
+        commands.entity(player).insert(Buddha);
+        Ok("buddha mode ON".into())
+    }
+}
+
+fn remove_cheats_without_sv_cheats(
+    console: Res<DeveloperConsole>,
+    cheaters: Query<Entity, Or<(With<GodMode>, With<Buddha>)>>,
+    mut commands: Commands,
+) {
+    if console.cheats_enabled() {
+        return;
+    }
+
+    for entity in cheaters.iter() {
+        commands.entity(entity).remove::<(GodMode, Buddha)>();
+    }
+}
+
+pub fn damage_modifiers_console_plugin(app: &mut App) {
+    app.add_cvar("mp_friendlyfire", false)
+        .add_cheat_command("god", god_command)
+        .add_cheat_command("buddha", buddha_command);
+}
+
+pub fn damage_modifiers_plugin(app: &mut App) {
+    app.add_plugins(damage_modifiers_console_plugin)
+        .add_cvar("cl_team", 0u8)
+        .add_systems(Update, (assign_player_teams, remove_cheats_without_sv_cheats))
+        .observe(friendly_fire_modifier)
+        .observe(spawn_protection_modifier)
+        .observe(god_mode_modifier)
+        .observe(resistance_modifier)
+        .observe(hitbox_region_modifier);
+}
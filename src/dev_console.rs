@@ -1,8 +1,15 @@
 use std::collections::VecDeque;
+use std::io::BufRead;
+use std::sync::mpsc;
+use std::thread;
+use bevy::log::BoxedLayer;
+use bevy::log::tracing_subscriber::layer::Context;
+use bevy::log::tracing_subscriber::Layer;
+use bevy::ecs::system::SystemId;
 use bevy::prelude::*;
-use bevy::utils::HashMap;
-use bevy_egui::{egui, EguiContexts};
-use bevy_egui::egui::{Key, ScrollArea, TextEdit};
+use bevy::utils::{HashMap, HashSet};
+use bevy::utils::tracing::{Level, Subscriber};
+use bevy::utils::tracing::field::{Field, Visit};
 
 pub trait DeveloperConsoleValue: Sized {
     fn dev_console_parse(source: &str) -> Result<Self, String>;
@@ -44,10 +51,13 @@ simple_dev_console_value!(i64);
 simple_dev_console_value!(i128);
 simple_dev_console_value!(f32);
 simple_dev_console_value!(f64);
+simple_dev_console_value!(Level);
 
-enum DevConsoleLineSource {
+pub enum DevConsoleLineSource {
     System,
-    User
+    User,
+    Error,
+    Log(Level),
 }
 
 impl DevConsoleLineSource {
@@ -55,6 +65,8 @@ impl DevConsoleLineSource {
         match self {
             DevConsoleLineSource::System => ">",
             DevConsoleLineSource::User => "$",
+            DevConsoleLineSource::Error => "!",
+            DevConsoleLineSource::Log(_) => "#",
         }
     }
 }
@@ -62,20 +74,29 @@ impl DevConsoleLineSource {
 #[derive(Resource)]
 pub struct DeveloperConsole {
     values: HashMap<String, String>,
+    // Cheat protected values, along with what they are reset to when cheats are turned off
+    cheat_defaults: HashMap<String, String>,
+    // Values only the server gets to change, sent on to clients over the netcode
+    server_values: HashSet<String>,
+    // Whether this is the server's own console, which is where server values do get changed
+    is_server: bool,
     lines: VecDeque<(DevConsoleLineSource, String)>,
+    total_lines: usize,
     history_allowed: usize,
-    show: bool,
-    buf: String
+    pending_commands: VecDeque<String>,
 }
 
 impl DeveloperConsole {
     pub fn new(history_allowed: usize) -> DeveloperConsole {
         Self {
             values: HashMap::new(),
+            cheat_defaults: HashMap::new(),
+            server_values: HashSet::new(),
+            is_server: false,
             lines: VecDeque::with_capacity(history_allowed),
+            total_lines: 0,
             history_allowed,
-            show: false,
-            buf: String::new()
+            pending_commands: VecDeque::new(),
         }
     }
 
@@ -93,88 +114,431 @@ impl DeveloperConsole {
         self.values.insert(key.to_string(), value.console_to_string());
     }
 
+    /// Sets the value, and only lets it be changed from the console while `sv_cheats` is on
+    pub fn set_cheat_value<T: DeveloperConsoleValue>(&mut self, key: &str, value: T) {
+        self.cheat_defaults.insert(key.to_string(), value.console_to_string());
+        self.set_value(key, value);
+    }
+
+    /// Sets the value, and stops it being changed from the console since the server decides it
+    pub fn set_server_value<T: DeveloperConsoleValue>(&mut self, key: &str, value: T) {
+        self.server_values.insert(key.to_string());
+        self.set_value(key, value);
+    }
+
+    /// Makes this the server's console, letting server values be changed from it
+    pub fn run_as_server(&mut self) {
+        self.is_server = true;
+    }
+
+    /// Every server value along with what it's currently set to, for sending on to clients
+    pub fn server_values(&self) -> Vec<(String, String)> {
+        let mut values: Vec<_> = self.server_values.iter()
+            .filter_map(|key| Some((key.clone(), self.values.get(key)?.clone())))
+            .collect();
+        values.sort();
+        values
+    }
+
+    pub fn cheats_enabled(&self) -> bool {
+        self.get_value::<bool>("sv_cheats").unwrap_or(false)
+    }
+
+    /// Sets `sv_cheats`, putting every cheat protected value back to its default when turned off
+    pub fn set_cheats_enabled(&mut self, enabled: bool) {
+        self.set_value("sv_cheats", enabled);
+
+        if !enabled {
+            let cheat_defaults = self.cheat_defaults.clone();
+            for (key, default) in cheat_defaults {
+                self.set_value(&key, default);
+            }
+        }
+    }
+
+    fn set_value_from_console(&mut self, key: &str, value: &str) -> ConsoleCommandResult {
+        if self.server_values.contains(key) && !self.is_server {
+            return Err(format!("{key} can only be changed on the server"));
+        }
+        if self.cheat_defaults.contains_key(key) && !self.cheats_enabled() {
+            return Err(format!("{key} is cheat protected, set sv_cheats true first"));
+        }
+
+        if key == "sv_cheats" {
+            let enabled = bool::dev_console_parse(value)?;
+            self.set_cheats_enabled(enabled);
+        } else {
+            self.set_value(key, value.to_string());
+        }
+        Ok(String::new())
+    }
+
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.push_line((DevConsoleLineSource::System, line.into()));
+    }
+
+    /// Prints what a command returned, a line at a time for output and as an error otherwise
+    pub fn print_result(&mut self, result: ConsoleCommandResult) {
+        match result {
+            Ok(output) => {
+                for line in output.lines() {
+                    self.print(line);
+                }
+            }
+            Err(err) => self.push_line((DevConsoleLineSource::Error, err)),
+        }
+    }
+
+    /// Echoes the line as user input, then queues it to be run
+    pub fn submit(&mut self, command: impl Into<String>) {
+        let command = command.into();
+        self.push_line((DevConsoleLineSource::User, command.clone()));
+        if !command.trim().is_empty() {
+            self.pending_commands.push_back(command);
+        }
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &(DevConsoleLineSource, String)> {
+        self.lines.iter()
+    }
+
+    /// Lines pushed since `seen` lines had been pushed in total, along with the new total
+    pub fn lines_since(&self, seen: usize) -> (impl Iterator<Item = &(DevConsoleLineSource, String)>, usize) {
+        let unseen = (self.total_lines - seen).min(self.lines.len());
+        (self.lines.iter().skip(self.lines.len() - unseen), self.total_lines)
+    }
+
     fn push_line(&mut self, line: (DevConsoleLineSource, String)) {
         self.lines.push_back(line);
+        self.total_lines += 1;
         if self.lines.len() > self.history_allowed {
             self.lines.pop_front();
         }
     }
 }
 
-fn listen_for_dev_console_enable(
-    keys: Res<ButtonInput<KeyCode>>,
-    mut developer_console: ResMut<DeveloperConsole>
+/// What a console command reports back, printed to the console and handed to whoever ran it
+pub type ConsoleCommandResult = Result<String, String>;
+
+// Console commands are registered one-shot systems, handed the arguments following the command name
+struct ConsoleCommand {
+    system_id: SystemId<Vec<String>, ConsoleCommandResult>,
+    cheat: bool,
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+struct ConsoleCommands(HashMap<String, ConsoleCommand>);
+
+/// Runs a single console line against the world, without printing anything
+pub fn run_console_command(world: &mut World, command: &str) -> ConsoleCommandResult {
+    let mut tokens = command.split_whitespace().map(String::from);
+    let Some(name) = tokens.next() else {
+        return Ok(String::new());
+    };
+    let args = tokens.collect::<Vec<String>>();
+
+    let registered_command = world.get_resource::<ConsoleCommands>()
+        .and_then(|commands| commands.get(&name))
+        .map(|command| (command.system_id, command.cheat));
+    if let Some((system_id, cheat)) = registered_command {
+        let cheats_enabled = world.get_resource::<DeveloperConsole>().is_some_and(DeveloperConsole::cheats_enabled);
+        if cheat && !cheats_enabled {
+            return Err(format!("{name} is cheat protected, set sv_cheats true first"));
+        }
+        return world.run_system_with_input(system_id, args)
+            .map_err(|err| format!("Could not run console command {name}: {err:?}"))?;
+    }
+
+    let mut developer_console = world.get_resource_mut::<DeveloperConsole>().ok_or("No developer console")?;
+    match &args[..] {
+        [] => match developer_console.values.get(&name) {
+            Some(value) => Ok(format!("{name} = {value}")),
+            None => Err(format!("Unknown command or value: {name}")),
+        },
+        [value] => developer_console.set_value_from_console(&name, value),
+        _ => Err(format!("Incorrect command: {command}")),
+    }
+}
+
+fn execute_console_commands(world: &mut World) {
+    let pending_commands = world.resource_mut::<DeveloperConsole>().pending_commands.drain(..).collect::<Vec<String>>();
+
+    for command in pending_commands {
+        let result = run_console_command(world, &command);
+        world.resource_mut::<DeveloperConsole>().print_result(result);
+    }
+}
+
+// Log records are handed from the tracing layer to the app over a channel, as the layer lives outside the ECS
+struct CapturedLogRecords(mpsc::Receiver<(Level, String)>);
+
+struct ConsoleLogLayer {
+    sender: mpsc::Sender<(Level, String)>,
+}
+
+#[derive(Default)]
+struct LogMessageVisitor(String);
+
+impl Visit for LogMessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{value:?}");
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for ConsoleLogLayer {
+    fn on_event(&self, event: &bevy::utils::tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = LogMessageVisitor::default();
+        event.record(&mut visitor);
+        // The receiving end only goes away when the app does, nothing to be done about it then
+        let _ = self.sender.send((*event.metadata().level(), visitor.0));
+    }
+}
+
+/// Used as the `custom_layer` of the `LogPlugin` to mirror log output into the developer console
+pub fn console_log_layer(app: &mut App) -> Option<BoxedLayer> {
+    let (sender, receiver) = mpsc::channel();
+    app.insert_non_send_resource(CapturedLogRecords(receiver));
+
+    Some(Box::new(ConsoleLogLayer { sender }))
+}
+
+fn transfer_log_records(
+    captured_log_records: Option<NonSend<CapturedLogRecords>>,
+    mut developer_console: ResMut<DeveloperConsole>,
 ) {
-    if keys.just_pressed(KeyCode::Backquote) {
-        developer_console.show = !developer_console.show;
-    }
-}
-
-fn dev_console_ui(mut ctx: EguiContexts, mut developer_console: ResMut<DeveloperConsole>) {
-    if developer_console.show && ctx.try_ctx_mut().is_some() {
-        egui::Window::new("Dev Console")
-            .default_size([512., 512.])
-            .title_bar(true)
-            .resizable(true)
-            .show(ctx.ctx_mut(), |ui| {
-                ui.vertical(|ui| {
-                    let scroll_height = ui.available_height() - 30.0;
-
-                    ScrollArea::vertical()
-                        .auto_shrink([false, false])
-                        .stick_to_bottom(true)
-                        .max_height(scroll_height)
-                        .show(ui, |ui| {
-                            for line in &developer_console.lines {
-                                ui.label(format!("{}{}", line.0.get_line_symbol(), line.1));
-                            }
-                        });
-                });
-
-                ui.separator();
-
-                let text_edit = TextEdit::singleline(&mut developer_console.buf)
-                    .desired_width(f32::INFINITY)
-                    .lock_focus(true)
-                    .font(egui::TextStyle::Monospace);
-
-                let text_edit_response = ui.add(text_edit);
-
-                if text_edit_response.lost_focus() && ui.input(|ui| ui.key_pressed(Key::Enter)) {
-                    if developer_console.buf.is_empty() {
-                        developer_console.push_line((DevConsoleLineSource::User, String::new()));
-                    } else {
-                        let command = developer_console.buf.trim().to_string();
-                        developer_console.push_line((DevConsoleLineSource::User, command.clone()));
-                        let tokens = command.split(" ").collect::<Vec<&str>>();
-                        if tokens.len() < 2 {
-                            developer_console.push_line((DevConsoleLineSource::System, format!("Incorrect command: {:?}", tokens)));
-                            return;
-                        }
-                        developer_console.set_value(tokens[0], tokens[1].to_string());
-                        developer_console.buf.clear();
-                    }
-                }
-        });
+    let Some(captured_log_records) = captured_log_records else {
+        return;
+    };
+
+    let log_level = developer_console.get_value::<Level>("log_level").unwrap_or(Level::INFO);
+    for (level, message) in captured_log_records.0.try_iter() {
+        if level <= log_level {
+            developer_console.push_line((DevConsoleLineSource::Log(level), message));
+        }
     }
 }
 
+// Lines typed into the terminal, read on their own thread as reading stdin blocks
+struct StdinLines(mpsc::Receiver<String>);
+
+fn read_stdin_lines(
+    stdin_lines: NonSend<StdinLines>,
+    mut developer_console: ResMut<DeveloperConsole>,
+) {
+    for line in stdin_lines.0.try_iter() {
+        developer_console.submit(line.trim());
+    }
+}
+
+fn print_console_lines_to_stdout(
+    developer_console: Res<DeveloperConsole>,
+    mut seen: Local<usize>,
+) {
+    let (lines, total_lines) = developer_console.lines_since(*seen);
+    // Log lines already made it to stdout through the regular log output
+    for (source, line) in lines.filter(|(source, _)| matches!(source, DevConsoleLineSource::System | DevConsoleLineSource::Error)) {
+        println!("{}{}", source.get_line_symbol(), line);
+    }
+    *seen = total_lines;
+}
+
+/// Drives the developer console from the terminal, for the headless server
+pub fn stdin_console_plugin(app: &mut App) {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    app.insert_non_send_resource(StdinLines(receiver))
+        .add_systems(PreUpdate, read_stdin_lines)
+        .add_systems(Last, print_console_lines_to_stdout);
+}
+
 pub trait AddConsoleVariable {
-    fn add_cvar<T: DeveloperConsoleValue + Copy + Send + Sync + 'static>(&mut self, key: &'static str, value: T) -> &mut Self;
+    fn add_cvar<T: DeveloperConsoleValue + Clone + Send + Sync + 'static>(&mut self, key: &'static str, value: T) -> &mut Self;
+    fn add_cheat_cvar<T: DeveloperConsoleValue + Clone + Send + Sync + 'static>(&mut self, key: &'static str, value: T) -> &mut Self;
+    fn add_server_cvar<T: DeveloperConsoleValue + Clone + Send + Sync + 'static>(&mut self, key: &'static str, value: T) -> &mut Self;
 }
 
 impl AddConsoleVariable for App {
-    fn add_cvar<T: DeveloperConsoleValue + Copy + Send + Sync + 'static>(&mut self, key: &'static str, value: T) -> &mut Self {
+    fn add_cvar<T: DeveloperConsoleValue + Clone + Send + Sync + 'static>(&mut self, key: &'static str, value: T) -> &mut Self {
+        let system = move |mut developer_console: ResMut<DeveloperConsole>| {
+            developer_console.set_value(key, value.clone());
+        };
+
+        self.add_systems(Startup, system)
+    }
+
+    fn add_cheat_cvar<T: DeveloperConsoleValue + Clone + Send + Sync + 'static>(&mut self, key: &'static str, value: T) -> &mut Self {
         let system = move |mut developer_console: ResMut<DeveloperConsole>| {
-            developer_console.set_value(key, value);
+            developer_console.set_cheat_value(key, value.clone());
         };
 
         self.add_systems(Startup, system)
     }
+
+    fn add_server_cvar<T: DeveloperConsoleValue + Clone + Send + Sync + 'static>(&mut self, key: &'static str, value: T) -> &mut Self {
+        let system = move |mut developer_console: ResMut<DeveloperConsole>| {
+            developer_console.set_server_value(key, value.clone());
+        };
+
+        self.add_systems(Startup, system)
+    }
+}
+
+pub trait AddConsoleCommand {
+    fn add_console_command<M>(&mut self, name: &'static str, command: impl IntoSystem<Vec<String>, ConsoleCommandResult, M> + 'static) -> &mut Self;
+    fn add_cheat_command<M>(&mut self, name: &'static str, command: impl IntoSystem<Vec<String>, ConsoleCommandResult, M> + 'static) -> &mut Self;
+}
+
+fn register_console_command<M>(app: &mut App, name: &'static str, command: impl IntoSystem<Vec<String>, ConsoleCommandResult, M> + 'static, cheat: bool) {
+    let system_id = app.world_mut().register_system(command);
+    app.world_mut()
+        .get_resource_or_insert_with(ConsoleCommands::default)
+        .insert(name.to_string(), ConsoleCommand { system_id, cheat });
+}
+
+impl AddConsoleCommand for App {
+    fn add_console_command<M>(&mut self, name: &'static str, command: impl IntoSystem<Vec<String>, ConsoleCommandResult, M> + 'static) -> &mut Self {
+        register_console_command(self, name, command, false);
+        self
+    }
+
+    fn add_cheat_command<M>(&mut self, name: &'static str, command: impl IntoSystem<Vec<String>, ConsoleCommandResult, M> + 'static) -> &mut Self {
+        register_console_command(self, name, command, true);
+        self
+    }
 }
 
 pub fn developer_console_plugin(app: &mut App) {
     app
         .insert_resource(DeveloperConsole::new(40))
-        .add_systems(Update, (listen_for_dev_console_enable, dev_console_ui));
+        .add_cvar("log_level", Level::INFO)
+        .add_cvar("sv_cheats", false)
+        .add_systems(Update, transfer_log_records)
+        .add_systems(PostUpdate, execute_console_commands);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo(In(args): In<Vec<String>>) -> ConsoleCommandResult {
+        Ok(args.join(" "))
+    }
+
+    fn console_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(developer_console_plugin)
+            .add_cvar("mp_test", 5i32)
+            .add_cheat_cvar("cheat_test", false)
+            .add_console_command("echo", echo)
+            .add_cheat_command("cheat_echo", echo);
+        // Cvars are only registered once the startup systems have run
+        app.update();
+        app
+    }
+
+    #[test]
+    fn sets_and_gets_cvars() {
+        let mut app = console_app();
+
+        assert_eq!(run_console_command(app.world_mut(), "mp_test 7"), Ok(String::new()));
+        assert_eq!(run_console_command(app.world_mut(), "mp_test"), Ok("mp_test = 7".to_string()));
+        assert_eq!(app.world().resource::<DeveloperConsole>().get_value::<i32>("mp_test"), Ok(7));
+    }
+
+    #[test]
+    fn unknown_commands_are_errors() {
+        let mut app = console_app();
+
+        assert_eq!(run_console_command(app.world_mut(), "nope"), Err("Unknown command or value: nope".to_string()));
+        assert_eq!(run_console_command(app.world_mut(), "mp_test 1 2"), Err("Incorrect command: mp_test 1 2".to_string()));
+    }
+
+    #[test]
+    fn commands_return_their_output() {
+        let mut app = console_app();
+
+        assert_eq!(run_console_command(app.world_mut(), "echo hello world"), Ok("hello world".to_string()));
+    }
+
+    #[test]
+    fn cheats_need_sv_cheats() {
+        let mut app = console_app();
+
+        assert_eq!(
+            run_console_command(app.world_mut(), "cheat_test true"),
+            Err("cheat_test is cheat protected, set sv_cheats true first".to_string()),
+        );
+        assert_eq!(
+            run_console_command(app.world_mut(), "cheat_echo hi"),
+            Err("cheat_echo is cheat protected, set sv_cheats true first".to_string()),
+        );
+
+        assert_eq!(run_console_command(app.world_mut(), "sv_cheats true"), Ok(String::new()));
+        assert_eq!(run_console_command(app.world_mut(), "cheat_test true"), Ok(String::new()));
+        assert_eq!(run_console_command(app.world_mut(), "cheat_echo hi"), Ok("hi".to_string()));
+
+        // Turning cheats back off puts everything back how it was
+        assert_eq!(run_console_command(app.world_mut(), "sv_cheats false"), Ok(String::new()));
+        assert_eq!(app.world().resource::<DeveloperConsole>().get_value::<bool>("cheat_test"), Ok(false));
+    }
+
+    #[test]
+    fn server_values_cannot_be_set_locally() {
+        let mut app = console_app();
+        app.world_mut().resource_mut::<DeveloperConsole>().set_server_value("sv_cheats", false);
+
+        assert_eq!(
+            run_console_command(app.world_mut(), "sv_cheats true"),
+            Err("sv_cheats can only be changed on the server".to_string()),
+        );
+
+        // Only the server's word turns cheats on
+        app.world_mut().resource_mut::<DeveloperConsole>().set_cheats_enabled(true);
+        assert_eq!(run_console_command(app.world_mut(), "cheat_test true"), Ok(String::new()));
+        app.world_mut().resource_mut::<DeveloperConsole>().set_cheats_enabled(false);
+        assert_eq!(app.world().resource::<DeveloperConsole>().get_value::<bool>("cheat_test"), Ok(false));
+    }
+
+    #[test]
+    fn server_values_can_be_set_on_the_server() {
+        let mut app = console_app();
+        let mut console = app.world_mut().resource_mut::<DeveloperConsole>();
+        console.set_server_value("sv_regen_rate", 5.0f32);
+        console.run_as_server();
+
+        assert_eq!(run_console_command(app.world_mut(), "sv_regen_rate 10"), Ok(String::new()));
+        assert_eq!(
+            app.world().resource::<DeveloperConsole>().server_values(),
+            vec![("sv_regen_rate".to_string(), "10".to_string())],
+        );
+    }
+
+    #[test]
+    fn submitted_commands_print_their_result() {
+        let mut app = console_app();
+
+        app.world_mut().resource_mut::<DeveloperConsole>().submit("echo printed");
+        app.world_mut().resource_mut::<DeveloperConsole>().submit("nope");
+        app.update();
+
+        let console = app.world().resource::<DeveloperConsole>();
+        let lines: Vec<(&str, &str)> = console.lines()
+            .map(|(source, line)| (source.get_line_symbol(), line.as_str()))
+            .collect();
+        assert_eq!(lines, [
+            ("$", "echo printed"),
+            ("$", "nope"),
+            (">", "printed"),
+            ("!", "Unknown command or value: nope"),
+        ]);
+    }
 }
@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+use bevy::utils::tracing::Level;
+use bevy_egui::{egui, EguiContexts};
+use bevy_egui::egui::{Color32, Key, RichText, ScrollArea, TextEdit};
+use crate::dev_console::{DevConsoleLineSource, DeveloperConsole};
+use crate::input::{ActionInput, InputAction};
+
+#[derive(Resource, Default)]
+pub struct DeveloperConsoleUi {
+    show: bool,
+    buf: String,
+}
+
+fn get_line_colour(source: &DevConsoleLineSource) -> Color32 {
+    match source {
+        DevConsoleLineSource::System | DevConsoleLineSource::User => Color32::LIGHT_GRAY,
+        DevConsoleLineSource::Error => Color32::LIGHT_RED,
+        DevConsoleLineSource::Log(level) => match *level {
+            Level::ERROR => Color32::LIGHT_RED,
+            Level::WARN => Color32::YELLOW,
+            Level::INFO => Color32::WHITE,
+            Level::DEBUG => Color32::LIGHT_BLUE,
+            _ => Color32::GRAY,
+        },
+    }
+}
+
+fn listen_for_dev_console_enable(
+    actions: ActionInput,
+    mut developer_console_ui: ResMut<DeveloperConsoleUi>
+) {
+    if actions.just_pressed(InputAction::ToggleConsole) {
+        developer_console_ui.show = !developer_console_ui.show;
+    }
+}
+
+fn dev_console_ui(
+    mut ctx: EguiContexts,
+    mut developer_console: ResMut<DeveloperConsole>,
+    mut developer_console_ui: ResMut<DeveloperConsoleUi>,
+) {
+    if developer_console_ui.show && ctx.try_ctx_mut().is_some() {
+        egui::Window::new("Dev Console")
+            .default_size([512., 512.])
+            .title_bar(true)
+            .resizable(true)
+            .show(ctx.ctx_mut(), |ui| {
+                ui.vertical(|ui| {
+                    let scroll_height = ui.available_height() - 30.0;
+
+                    ScrollArea::vertical()
+                        .auto_shrink([false, false])
+                        .stick_to_bottom(true)
+                        .max_height(scroll_height)
+                        .show(ui, |ui| {
+                            for line in developer_console.lines() {
+                                ui.label(RichText::new(format!("{}{}", line.0.get_line_symbol(), line.1))
+                                    .color(get_line_colour(&line.0)));
+                            }
+                        });
+                });
+
+                ui.separator();
+
+                let text_edit = TextEdit::singleline(&mut developer_console_ui.buf)
+                    .desired_width(f32::INFINITY)
+                    .lock_focus(true)
+                    .font(egui::TextStyle::Monospace);
+
+                let text_edit_response = ui.add(text_edit);
+
+                if text_edit_response.lost_focus() && ui.input(|ui| ui.key_pressed(Key::Enter)) {
+                    developer_console.submit(developer_console_ui.buf.trim());
+                    developer_console_ui.buf.clear();
+                }
+        });
+    }
+}
+
+pub fn developer_console_ui_plugin(app: &mut App) {
+    app.insert_resource(DeveloperConsoleUi::default())
+        .add_systems(Update, (listen_for_dev_console_enable, dev_console_ui));
+}
@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::dev_console::{AddConsoleVariable, DeveloperConsole};
+use crate::health::{DamageSource, DamageType, Health, PotentialDamageEvent};
+use crate::player::PlayerVelocity;
+
+/// Triggered globally, damages and pushes away everything within `radius` that the blast can reach
+#[derive(Event)]
+pub struct ExplosionEvent {
+    pub originating_entity: Entity,
+    pub source: DamageSource,
+    pub position: Vec3,
+    pub radius: f32,
+    /// Damage at the very centre, falling off linearly to nothing at `radius`
+    pub damage: i32,
+    /// Impulse at the very centre, falling off the same way as damage
+    pub knockback: f32,
+}
+
+fn handle_explosions(
+    trigger: Trigger<ExplosionEvent>,
+    rapier_context: Res<RapierContext>,
+    transforms: Query<&GlobalTransform>,
+    damageable: Query<(), With<Health>>,
+    mut velocities: Query<&mut PlayerVelocity>,
+    console: Res<DeveloperConsole>,
+    mut commands: Commands,
+) {
+    let explosion = trigger.event();
+    debug!("Explosion at {} with radius {}", explosion.position, explosion.radius);
+
+    let mut caught_in_blast = Vec::new();
+    rapier_context.intersections_with_shape(
+        explosion.position,
+        Quat::IDENTITY,
+        &Collider::ball(explosion.radius),
+        // Hitboxes are for aimed shots, a blast goes by the body it catches
+        QueryFilter::default().exclude_sensors(),
+        |entity| {
+            caught_in_blast.push(entity);
+            true
+        },
+    );
+
+    let self_damage_scale = console.get_value::<f32>("sv_self_damage_scale").unwrap_or(1.0);
+    // Only the map blocks a blast, players, projectiles and other damageable things don't shield one another
+    let map_only = |entity: Entity| !damageable.contains(entity);
+
+    for entity in caught_in_blast {
+        // The map, debris and projectiles have nothing to hurt or push around
+        if !damageable.contains(entity) {
+            continue;
+        }
+
+        // Measured to the nearest bit of the collider, brush entities all sit at the map origin
+        let only_entity = |other: Entity| other == entity;
+        let closest_point = rapier_context.project_point(explosion.position, true, QueryFilter::default().predicate(&only_entity))
+            .map(|(_, projection)| projection.point)
+            .or_else(|| transforms.get(entity).ok().map(GlobalTransform::translation));
+        let Some(closest_point) = closest_point else {
+            continue;
+        };
+
+        let offset = closest_point - explosion.position;
+        let distance = offset.length();
+        if distance > 0.0 && rapier_context.cast_ray(
+            explosion.position,
+            offset / distance,
+            distance,
+            true,
+            QueryFilter::only_fixed().exclude_sensors().exclude_collider(entity).predicate(&map_only),
+        ).is_some() {
+            continue;
+        }
+
+        let falloff = (1.0 - distance / explosion.radius).clamp(0.0, 1.0);
+
+        if let Ok(mut velocity) = velocities.get_mut(entity) {
+            // Caught dead centre, the only sensible way to go is up
+            let direction = offset.try_normalize().unwrap_or(Vec3::Y);
+            velocity.apply_impulse(direction * explosion.knockback * falloff);
+        }
+
+        let mut damage = explosion.damage as f32 * falloff;
+        if entity == explosion.originating_entity {
+            damage *= self_damage_scale;
+        }
+        let damage = damage.round() as i32;
+        if damage > 0 {
+            commands.trigger_targets(PotentialDamageEvent {
+                damage,
+                originating_entity: explosion.originating_entity,
+                damage_type: DamageType::Explosive,
+                source: explosion.source.clone(),
+                hit_region: None,
+                origin: Some(explosion.position),
+            }, entity);
+        }
+    }
+}
+
+pub fn explosion_console_plugin(app: &mut App) {
+    app.add_cvar("sv_self_damage_scale", 0.5f32);
+}
+
+pub fn explosion_plugin(app: &mut App) {
+    app.add_plugins(explosion_console_plugin)
+        .add_event::<ExplosionEvent>()
+        .observe(handle_explosions);
+}
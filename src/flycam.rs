@@ -3,6 +3,7 @@ use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, PrimaryWindow};
 use bevy_rapier3d::prelude::*;
+use crate::input::{ActionInput, InputAction};
 
 #[derive(Component)]
 pub struct FlyCam;
@@ -28,34 +29,39 @@ impl Default for FlyCamSettings {
 }
 
 fn handle_fly_cam(
-    mut cameras: Query<&mut Transform, With<Camera>>,
-    keys: Res<ButtonInput<KeyCode>>,
+    mut cameras: Query<(&mut Transform, &Camera), With<FlyCam>>,
+    actions: ActionInput,
     time: Res<Time>,
     settings: Res<FlyCamSettings>,
 ) {
-    for mut camera_transform in &mut cameras {
+    for (mut camera_transform, camera) in &mut cameras {
+        // Sits still until something switches over to it
+        if !camera.is_active {
+            continue;
+        }
+
         let mut translation = Vect::ZERO;
         let forward = camera_transform.forward();
         let backward = camera_transform.back();
         let left = camera_transform.left();
         let right = camera_transform.right();
 
-        if keys.pressed(KeyCode::KeyA) {
+        if actions.pressed(InputAction::MoveLeft) {
             translation += left.as_vec3();
         }
-        if keys.pressed(KeyCode::KeyD) {
+        if actions.pressed(InputAction::MoveRight) {
             translation += right.as_vec3();
         }
-        if keys.pressed(KeyCode::KeyW) {
+        if actions.pressed(InputAction::MoveForward) {
             translation += forward.as_vec3();
         }
-        if keys.pressed(KeyCode::KeyS) {
+        if actions.pressed(InputAction::MoveBack) {
             translation += backward.as_vec3();
         }
-        if keys.pressed(KeyCode::Space) {
+        if actions.pressed(InputAction::Jump) {
             translation += Vec3::Y;
         }
-        if keys.pressed(KeyCode::ShiftLeft) {
+        if actions.pressed(InputAction::Crouch) {
             translation -= Vec3::Y;
         }
 
@@ -65,14 +71,18 @@ fn handle_fly_cam(
 }
 
 fn fly_camera_look(
-    mut fly_cam: Query<&mut Transform, With<FlyCam>>,
+    mut fly_cam: Query<(&mut Transform, &Camera), With<FlyCam>>,
     camera_settings: Res<FlyCamSettings>,
     primary_window: Query<&Window, With<PrimaryWindow>>,
     mut state: ResMut<InputState>,
     motion: Res<Events<MouseMotion>>,
 ) {
     if let Ok(window) = primary_window.get_single() {
-        for mut transform in fly_cam.iter_mut() {
+        for (mut transform, camera) in fly_cam.iter_mut() {
+            if !camera.is_active {
+                state.reader_motion.clear(&motion);
+                continue;
+            }
             for event in state.reader_motion.read(&motion) {
                 let (mut yaw, mut pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
                 match window.cursor.grab_mode {
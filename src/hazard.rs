@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::health::{DamageSource, DamageType, PotentialDamageEvent};
+use crate::player::Player;
+use crate::respawn::Dead;
+
+/// Brush volume that keeps hurting players inside it, like lava, a pit or a kill volume
+#[derive(Component)]
+pub struct TriggerHurt {
+    damage: i32,
+    damage_type: DamageType,
+    interval: Timer,
+}
+
+pub trait MakeEntityTriggerHurt {
+    fn make_trigger_hurt(&mut self, damage: i32, interval: f32, damage_type: &str) -> &mut Self;
+}
+
+impl MakeEntityTriggerHurt for EntityWorldMut<'_> {
+    fn make_trigger_hurt(&mut self, damage: i32, interval: f32, damage_type: &str) -> &mut Self {
+        let damage_type = DamageType::from_name(damage_type).unwrap_or_else(|| {
+            warn!("Unknown trigger_hurt damage type '{damage_type}', using environment");
+            DamageType::Environment
+        });
+
+        self.insert((
+            TriggerHurt {
+                damage,
+                damage_type,
+                interval: Timer::from_seconds(interval, TimerMode::Repeating),
+            },
+            Sensor,
+            // Players move with a character controller rather than a rigid body, so they count as static as well
+            ActiveCollisionTypes::default() | ActiveCollisionTypes::STATIC_STATIC,
+        ))
+    }
+}
+
+fn hurt_players_in_triggers(
+    mut triggers: Query<(Entity, &mut TriggerHurt)>,
+    players: Query<Entity, (With<Player>, Without<Dead>)>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (trigger_entity, mut trigger_hurt) in triggers.iter_mut() {
+        if !trigger_hurt.interval.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        for player in players.iter() {
+            if rapier_context.intersection_pair(trigger_entity, player) != Some(true) {
+                continue;
+            }
+
+            commands.trigger_targets(PotentialDamageEvent {
+                originating_entity: trigger_entity,
+                damage: trigger_hurt.damage,
+                damage_type: trigger_hurt.damage_type,
+                source: DamageSource::Hazard("trigger_hurt".into()),
+                hit_region: None,
+                origin: None,
+            }, player);
+        }
+    }
+}
+
+pub fn hazard_plugin(app: &mut App) {
+    app.add_systems(Update, hurt_players_in_triggers);
+}
@@ -1,44 +1,497 @@
+use std::collections::VecDeque;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::damage_modifiers::{damage_modifiers_plugin, Buddha};
+use crate::dev_console::{AddConsoleCommand, AddConsoleVariable, ConsoleCommandResult, DeveloperConsole};
+use crate::hitbox::HitboxRegion;
+use crate::player::Player;
+use crate::respawn::Dead;
+use crate::weapon::WeaponDefinition;
 
 #[derive(Component)]
 pub struct Health {
     pub max: i32,
+    /// Can go above `max` when overhealed, decaying back down over time
     pub current: i32,
+    /// Seconds since damage was last taken, regeneration waits on this
+    since_damaged: f32,
+    /// Fractional health built up by regeneration or lost to overheal decay, applied a whole point at a time
+    partial: f32,
+    /// Recent damage taken, oldest first, for working out who gets credit for the kill
+    damage_log: VecDeque<DamageRecord>,
 }
 
-#[derive(Event)]
+/// How long damage taken still counts towards a kill or an assist, in seconds
+const DAMAGE_LOG_TIME: f32 = 10.0;
+
+#[derive(Clone, Debug)]
+pub struct DamageRecord {
+    pub attacker: Entity,
+    pub damage: i32,
+    pub damage_type: DamageType,
+    pub source: DamageSource,
+    pub headshot: bool,
+    /// Elapsed seconds when the damage was taken
+    pub time: f32,
+}
+
+/// Soaks up part of incoming damage until it runs out
+#[derive(Component)]
+pub struct Armor {
+    pub max: i32,
+    pub current: i32,
+    /// Fraction of incoming damage taken by the armor instead of health
+    pub absorption: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DamageType {
+    Bullet,
+    Explosive,
+    Fall,
+    Melee,
+    Environment,
+}
+
+impl DamageType {
+    /// Parses the lowercase names used in map entity properties
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bullet" => Some(Self::Bullet),
+            "explosive" => Some(Self::Explosive),
+            "fall" => Some(Self::Fall),
+            "melee" => Some(Self::Melee),
+            "environment" => Some(Self::Environment),
+            _ => None,
+        }
+    }
+}
+
+/// What dealt the damage, for the kill feed and for modifiers that depend on the weapon
+#[derive(Clone, Debug, PartialEq)]
+pub enum DamageSource {
+    Weapon(AssetId<WeaponDefinition>),
+    /// Falling or a map hazard, named for the kill feed
+    Hazard(String),
+    /// Anything else that isn't a weapon
+    Other(String),
+}
+
+/// Triggered on an entity to try to damage it, modifiers get a say in how much of it actually gets through
+#[derive(Event, Clone, Debug)]
 pub struct PotentialDamageEvent {
     pub originating_entity: Entity,
     pub damage: i32,
+    pub damage_type: DamageType,
+    pub source: DamageSource,
+    /// Region of the hitbox that was struck, for damage that was aimed at someone rather than splashed on them
+    pub hit_region: Option<HitboxRegion>,
+    /// Where the damage came from, for showing the victim which way they were hit from
+    pub origin: Option<Vec3>,
+}
+
+impl PotentialDamageEvent {
+    pub fn is_headshot(&self) -> bool {
+        self.hit_region == Some(HitboxRegion::Head)
+    }
+}
+
+/// Triggered on the target of potential damage before it's dealt, observe it to scale or cancel the damage
+/// through the `DamageModification` resource
+#[derive(Event, Deref)]
+pub struct ModifyDamageEvent(pub PotentialDamageEvent);
+
+/// Only exists while `ModifyDamageEvent` is being triggered
+#[derive(Resource)]
+pub struct DamageModification {
+    multiplier: f32,
+    cancelled: bool,
 }
 
+impl Default for DamageModification {
+    fn default() -> Self {
+        Self {
+            multiplier: 1.0,
+            cancelled: false,
+        }
+    }
+}
+
+impl DamageModification {
+    pub fn scale(&mut self, multiplier: f32) {
+        self.multiplier *= multiplier;
+    }
+
+    pub fn cancel(&mut self) {
+        self.cancelled = true;
+    }
+}
+
+/// Damage that made it through the modifiers and is being dealt, armor still takes its share
+#[derive(Event, Deref)]
+pub struct DamageEvent(pub PotentialDamageEvent);
+
+/// Triggered on whatever just died, along with the blow that finished it off
 #[derive(Event)]
 pub struct DeathEvent {
-    originating_entity: Entity,
+    pub killing_blow: PotentialDamageEvent,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KillCause {
+    Player,
+    Suicide,
+    /// Falling or a map hazard, with nobody to blame for it
+    Environment,
+}
+
+/// Who gets the credit for a death, sent once for every one of them for the scoreboard, kill feed and stats
+#[derive(Event, Clone, Debug)]
+pub struct KillEvent {
+    pub victim: Entity,
+    pub killer: Option<Entity>,
+    /// Everyone else who damaged the victim shortly before they died
+    pub assisters: Vec<Entity>,
+    pub source: DamageSource,
+    pub damage_type: DamageType,
+    pub headshot: bool,
+    pub cause: KillCause,
 }
 
 impl Health {
     pub fn with_max(max: i32) -> Self {
-        Self { max, current: max }
+        Self {
+            max,
+            current: max,
+            since_damaged: 0.0,
+            partial: 0.0,
+            damage_log: VecDeque::new(),
+        }
+    }
+
+    /// Back to full health with a clean slate, for respawning
+    pub fn reset(&mut self) {
+        *self = Self::with_max(self.max);
+    }
+
+    pub fn damage_log(&self) -> impl Iterator<Item = &DamageRecord> {
+        self.damage_log.iter()
+    }
+
+    fn log_damage(&mut self, record: DamageRecord) {
+        let now = record.time;
+        self.damage_log.push_back(record);
+        while self.damage_log.front().is_some_and(|oldest| now - oldest.time > DAMAGE_LOG_TIME) {
+            self.damage_log.pop_front();
+        }
+    }
+
+    /// As far as a heal can take health, up to the `sv_overheal_max` cap past max with overheal
+    pub fn heal_cap(&self, overheal: bool, console: &DeveloperConsole) -> i32 {
+        if overheal {
+            self.max + console.get_value::<i32>("sv_overheal_max").unwrap_or(0)
+        } else {
+            self.max
+        }
+    }
+
+    /// Never takes away overheal that's already there
+    pub fn heal(&mut self, amount: i32, cap: i32) {
+        self.current = (self.current + amount).min(cap).max(self.current);
+    }
+
+    pub fn is_overhealed(&self) -> bool {
+        self.current > self.max
+    }
+
+    // Whole points out of `partial`, leaving the remainder for next time
+    fn take_partial(&mut self) -> i32 {
+        let whole = self.partial.trunc();
+        self.partial -= whole;
+        whole as i32
     }
 }
 
-fn handle_damage_events(
-    trigger: Trigger<PotentialDamageEvent>,
+/// Triggered on an entity to give it health back
+#[derive(Event)]
+pub struct HealEvent {
+    pub amount: i32,
+    /// Lets the heal go above max health, up to the `sv_overheal_max` cap
+    pub overheal: bool,
+}
+
+impl Armor {
+    /// Starts out with no armor at all, it has to be picked up
+    pub fn with_max(max: i32, absorption: f32) -> Self {
+        Self { max, current: 0, absorption }
+    }
+
+    /// Takes the armor's share out of `damage`, returning what's left over for health
+    pub fn absorb(&mut self, damage: i32) -> i32 {
+        let absorbed = ((damage as f32 * self.absorption).round() as i32).clamp(0, self.current);
+        self.current -= absorbed;
+        damage - absorbed
+    }
+}
+
+fn handle_heal_events(
+    trigger: Trigger<HealEvent>,
     mut health_query: Query<&mut Health>,
+    console: Res<DeveloperConsole>,
+) {
+    let Ok(mut health) = health_query.get_mut(trigger.entity()) else {
+        return;
+    };
+    // The dead need respawning, not healing
+    if health.current <= 0 {
+        return;
+    }
+
+    let cap = health.heal_cap(trigger.event().overheal, &console);
+    health.heal(trigger.event().amount, cap);
+}
+
+fn regenerate_health(
+    mut health_query: Query<&mut Health, (With<Player>, Without<Dead>)>,
+    console: Res<DeveloperConsole>,
+    time: Res<Time>,
+) {
+    let regen_rate = console.get_value::<f32>("sv_regen_rate").unwrap_or(0.0);
+    let regen_delay = console.get_value::<f32>("sv_regen_delay").unwrap_or(0.0);
+    let regen_limit = console.get_value::<f32>("sv_regen_limit").unwrap_or(1.0);
+    let overheal_decay = console.get_value::<f32>("sv_overheal_decay").unwrap_or(0.0);
+    let delta = time.delta_seconds();
+
+    for mut health in health_query.iter_mut() {
+        health.since_damaged += delta;
+
+        if health.is_overhealed() {
+            health.partial -= overheal_decay * delta;
+            let max = health.max;
+            let change = health.take_partial();
+            health.current = (health.current + change).max(max);
+            continue;
+        }
+
+        let limit = (health.max as f32 * regen_limit).round() as i32;
+        if regen_rate <= 0.0 || health.since_damaged < regen_delay || health.current >= limit {
+            health.partial = 0.0;
+            continue;
+        }
+        health.partial += regen_rate * delta;
+        let change = health.take_partial();
+        health.current = (health.current + change).min(limit);
+    }
+}
+
+// Modifiers are run to completion as a command, so the damage is only dealt once every one of them has had its say
+fn run_damage_modifiers(
+    trigger: Trigger<PotentialDamageEvent>,
+    mut commands: Commands,
+) {
+    let target = trigger.entity();
+    let event = trigger.event().clone();
+
+    commands.add(move |world: &mut World| {
+        world.insert_resource(DamageModification::default());
+        world.trigger_targets(ModifyDamageEvent(event.clone()), target);
+        let Some(modification) = world.remove_resource::<DamageModification>() else {
+            return;
+        };
+
+        let damage = (event.damage as f32 * modification.multiplier).round() as i32;
+        print_damage(world, target, &event, damage, modification.cancelled);
+
+        if modification.cancelled {
+            debug!("Damage to {target:?} from {:?} was cancelled", event.source);
+            return;
+        }
+        if damage > 0 {
+            world.trigger_targets(DamageEvent(PotentialDamageEvent { damage, ..event }), target);
+        }
+    });
+}
+
+// Prints every bit of damage along with what the modifiers made of it
+fn print_damage(world: &mut World, target: Entity, event: &PotentialDamageEvent, damage: i32, cancelled: bool) {
+    let mut console = world.resource_mut::<DeveloperConsole>();
+    if !console.get_value::<bool>("developer_damage").unwrap_or(false) {
+        return;
+    }
+
+    let headshot = if event.is_headshot() { " (headshot)" } else { "" };
+    let outcome = if cancelled { "cancelled".to_string() } else { format!("{damage} after modifiers") };
+    console.print(format!(
+        "{target:?} hit for {} {:?} damage{headshot} from {:?} via {:?}, {outcome}",
+        event.damage, event.damage_type, event.originating_entity, event.source,
+    ));
+}
+
+fn is_environmental(damage_type: DamageType, source: &DamageSource) -> bool {
+    matches!(damage_type, DamageType::Fall | DamageType::Environment) || matches!(source, DamageSource::Hazard(_))
+}
+
+/// Works out who killed `victim` from the blow that finished them and the damage they took before it
+fn attribute_kill(victim: Entity, health: &Health, killing_blow: &PotentialDamageEvent) -> KillEvent {
+    // Damage from other players, most recent first
+    let mut player_damage = health.damage_log().rev()
+        .filter(|record| record.attacker != victim && !is_environmental(record.damage_type, &record.source));
+
+    let (killer, cause) = if is_environmental(killing_blow.damage_type, &killing_blow.source) {
+        // Whoever knocked them into it gets the credit, if anyone did
+        match player_damage.next() {
+            Some(record) => (Some(record.attacker), KillCause::Player),
+            None => (None, KillCause::Environment),
+        }
+    } else if killing_blow.originating_entity == victim {
+        (None, KillCause::Suicide)
+    } else {
+        (Some(killing_blow.originating_entity), KillCause::Player)
+    };
+
+    let mut assisters = Vec::new();
+    for record in health.damage_log().rev() {
+        if record.attacker == victim || Some(record.attacker) == killer || is_environmental(record.damage_type, &record.source) {
+            continue;
+        }
+        if !assisters.contains(&record.attacker) {
+            assisters.push(record.attacker);
+        }
+    }
+
+    KillEvent {
+        victim,
+        killer,
+        assisters,
+        source: killing_blow.source.clone(),
+        damage_type: killing_blow.damage_type,
+        headshot: killing_blow.is_headshot(),
+        cause,
+    }
+}
+
+fn handle_damage_events(
+    trigger: Trigger<DamageEvent>,
+    mut health_query: Query<(&mut Health, Option<&mut Armor>, Has<Buddha>)>,
+    time: Res<Time>,
     mut commands: Commands,
 ) {
-    if let Ok(mut health) = health_query.get_mut(trigger.entity()) {
-        health.current -= trigger.event().damage;
+    if let Ok((mut health, armor, buddha)) = health_query.get_mut(trigger.entity()) {
+        // Already dead, more damage doesn't kill them again
+        if health.current <= 0 {
+            return;
+        }
+
+        let damage = match armor {
+            Some(mut armor) => armor.absorb(trigger.event().damage),
+            None => trigger.event().damage,
+        };
+        health.current -= damage;
+        if buddha {
+            health.current = health.current.max(1);
+        }
+        health.since_damaged = 0.0;
+        health.log_damage(DamageRecord {
+            attacker: trigger.event().originating_entity,
+            damage,
+            damage_type: trigger.event().damage_type,
+            source: trigger.event().source.clone(),
+            headshot: trigger.event().is_headshot(),
+            time: time.elapsed_seconds(),
+        });
 
         if health.current <= 0 {
-            commands.trigger_targets(DeathEvent { originating_entity: trigger.event().originating_entity }, trigger.entity());
+            commands.trigger_targets(DeathEvent { killing_blow: trigger.event().0.clone() }, trigger.entity());
         }
     }
 }
 
+// Every death gets its kill worked out here, however it came about
+fn send_kill_events(
+    trigger: Trigger<DeathEvent>,
+    health_query: Query<&Health>,
+    mut kill_events: EventWriter<KillEvent>,
+) {
+    let Ok(health) = health_query.get(trigger.entity()) else {
+        return;
+    };
+
+    let kill = attribute_kill(trigger.entity(), health, &trigger.event().killing_blow);
+    info!("{:?} killed by {:?} ({:?}) with {:?}, assisted by {:?}", kill.victim, kill.killer, kill.cause, kill.source, kill.assisters);
+    kill_events.send(kill);
+}
+
+fn hurt_command(
+    In(args): In<Vec<String>>,
+    player_query: Query<Entity, (With<Player>, Without<Dead>)>,
+    mut commands: Commands,
+) -> ConsoleCommandResult {
+    let [damage] = &args[..] else {
+        return Err("Usage: hurt <damage>".into());
+    };
+    let damage: i32 = damage.parse().map_err(|_| format!("Invalid damage {damage}"))?;
+    if damage <= 0 {
+        return Err("Damage has to be more than 0".into());
+    }
+    let player = player_query.get_single().map_err(|_| "No living player to hurt".to_string())?;
+
+    // Goes through the modifiers like any other damage, so god mode still protects against it
+    commands.trigger_targets(PotentialDamageEvent {
+        originating_entity: player,
+        damage,
+        damage_type: DamageType::Environment,
+        source: DamageSource::Other("hurt".into()),
+        hit_region: None,
+        origin: None,
+    }, player);
+    Ok(format!("Hurt for {damage}"))
+}
+
+fn kill_command(
+    In(_): In<Vec<String>>,
+    mut player_query: Query<(Entity, &mut Health), (With<Player>, Without<Dead>)>,
+    mut commands: Commands,
+) -> ConsoleCommandResult {
+    let (player, mut health) = player_query.get_single_mut().map_err(|_| "No living player to kill".to_string())?;
+
+    // Straight to dead, past the modifiers, armor and buddha mode alike, then dies the same as from any other damage
+    let killing_blow = PotentialDamageEvent {
+        originating_entity: player,
+        damage: health.current,
+        damage_type: DamageType::Environment,
+        source: DamageSource::Other("kill".into()),
+        hit_region: None,
+        origin: None,
+    };
+    health.current = 0;
+    commands.trigger_targets(DeathEvent { killing_blow }, player);
+    Ok("Killed".into())
+}
+
+/// Cvars and commands only, so the server can have them without running any of the health systems
+pub fn health_console_plugin(app: &mut App) {
+    app.add_server_cvar("sv_overheal_max", 50i32)
+        .add_server_cvar("sv_overheal_decay", 2.0f32)
+        .add_server_cvar("sv_regen_rate", 5.0f32)
+        .add_server_cvar("sv_regen_delay", 5.0f32)
+        .add_server_cvar("sv_regen_limit", 1.0f32)
+        .add_cheat_cvar("developer_damage", false)
+        .add_cheat_command("hurt", hurt_command)
+        .add_cheat_command("kill", kill_command);
+}
+
 pub fn health_plugin(app: &mut App) {
-    app.add_event::<PotentialDamageEvent>()
+    app.add_plugins(damage_modifiers_plugin)
+        .add_plugins(health_console_plugin)
+        .add_event::<PotentialDamageEvent>()
+        .add_event::<ModifyDamageEvent>()
+        .add_event::<DamageEvent>()
         .add_event::<DeathEvent>()
-        .observe(handle_damage_events);
+        .add_event::<KillEvent>()
+        .add_event::<HealEvent>()
+        .add_systems(Update, regenerate_health)
+        .observe(handle_heal_events)
+        .observe(run_damage_modifiers)
+        .observe(handle_damage_events)
+        .observe(send_kill_events);
 }
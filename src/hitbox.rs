@@ -0,0 +1,106 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+use crate::player::Player;
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HitboxRegion {
+    Head,
+    Torso,
+    Limbs,
+}
+
+/// Sensor collider attached to a player, hits on it are passed on to `owner`
+#[derive(Component)]
+pub struct Hitbox {
+    pub owner: Entity,
+    pub region: HitboxRegion,
+}
+
+/// Damage multiplier for each hitbox region, set per weapon
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct HitboxMultipliers {
+    pub head: f32,
+    pub torso: f32,
+    pub limbs: f32,
+}
+
+impl Default for HitboxMultipliers {
+    fn default() -> Self {
+        Self {
+            head: 2.0,
+            torso: 1.0,
+            limbs: 0.75,
+        }
+    }
+}
+
+impl HitboxMultipliers {
+    pub fn get(&self, region: HitboxRegion) -> f32 {
+        match region {
+            HitboxRegion::Head => self.head,
+            HitboxRegion::Torso => self.torso,
+            HitboxRegion::Limbs => self.limbs,
+        }
+    }
+}
+
+/// Decides what attacks can hit. Players are hit through their hitboxes rather than the body they move around with,
+/// and hitboxes are the only sensors worth hitting, the rest are trigger volumes like hazards
+#[derive(SystemParam)]
+pub struct HitTargets<'w, 's> {
+    player_bodies: Query<'w, 's, (), With<Player>>,
+    hitboxes: Query<'w, 's, &'static Hitbox>,
+    sensors: Query<'w, 's, (), With<Sensor>>,
+}
+
+impl HitTargets<'_, '_> {
+    pub fn hitbox(&self, entity: Entity) -> Option<&Hitbox> {
+        self.hitboxes.get(entity).ok()
+    }
+
+    pub fn is_trigger_volume(&self, entity: Entity) -> bool {
+        self.sensors.contains(entity) && !self.hitboxes.contains(entity)
+    }
+
+    /// Whether a ray or shape cast by `attacker` should stop at `entity`, never on their own hitboxes
+    /// or those of any player in `ignored`
+    pub fn is_hittable(&self, entity: Entity, attacker: Entity, ignored: &[Entity]) -> bool {
+        !self.player_bodies.contains(entity)
+            && !self.is_trigger_volume(entity)
+            && self.hitbox(entity).map_or(true, |hitbox| hitbox.owner != attacker && !ignored.contains(&hitbox.owner))
+    }
+}
+
+// Laid out to fit inside the player's round cylinder collider, which is 2.2 units tall
+fn player_hitboxes() -> [(HitboxRegion, Collider, Vec3); 3] {
+    [
+        (HitboxRegion::Head, Collider::ball(0.25), Vec3::new(0.0, 0.8, 0.0)),
+        (HitboxRegion::Torso, Collider::cuboid(0.4, 0.4, 0.25), Vec3::new(0.0, 0.15, 0.0)),
+        (HitboxRegion::Limbs, Collider::cuboid(0.35, 0.45, 0.25), Vec3::new(0.0, -0.7, 0.0)),
+    ]
+}
+
+fn attach_player_hitboxes(
+    players: Query<Entity, Added<Player>>,
+    mut commands: Commands,
+) {
+    for player in players.iter() {
+        commands.entity(player).with_children(|builder| {
+            for (region, collider, offset) in player_hitboxes() {
+                builder.spawn((
+                    Hitbox { owner: player, region },
+                    collider,
+                    Sensor,
+                    TransformBundle::from_transform(Transform::from_translation(offset)),
+                ));
+            }
+        });
+    }
+}
+
+pub fn hitbox_plugin(app: &mut App) {
+    app.add_systems(Update, attach_player_hitboxes);
+}
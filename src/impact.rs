@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::dev_console::{AddConsoleVariable, DeveloperConsole};
+use crate::lifetime::Lifetime;
+use crate::netcode::ServerMessage;
+
+const DECAL_SIZE: f32 = 0.12;
+// Lifted off the surface so the decal doesn't z-fight with it
+const DECAL_OFFSET: f32 = 0.005;
+// How far off the surface a replicated impact can be and still find what it hit
+const SURFACE_PROBE_DISTANCE: f32 = 0.05;
+const PLAYER_HIT_EFFECT_LIFETIME: f32 = 0.25;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImpactKind {
+    World,
+    Player,
+}
+
+/// Written wherever a shot lands, whether it was ours or someone else's
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ImpactEvent {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub kind: ImpactKind,
+    /// What was hit, so decals stay stuck to it and go away with it. Entities aren't the same across the network,
+    /// so this isn't replicated
+    pub entity: Option<Entity>,
+}
+
+/// `ImpactEvent` as it's sent over the network
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ReplicatedImpact {
+    position: [f32; 3],
+    normal: [f32; 3],
+    kind: ImpactKind,
+}
+
+impl From<&ImpactEvent> for ReplicatedImpact {
+    fn from(impact: &ImpactEvent) -> Self {
+        Self {
+            position: impact.position.to_array(),
+            normal: impact.normal.to_array(),
+            kind: impact.kind,
+        }
+    }
+}
+
+impl From<&ReplicatedImpact> for ImpactEvent {
+    fn from(impact: &ReplicatedImpact) -> Self {
+        Self {
+            position: Vec3::from_array(impact.position),
+            normal: Vec3::from_array(impact.normal),
+            kind: impact.kind,
+            entity: None,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ImpactAssets {
+    decal_mesh: Handle<Mesh>,
+    decal_material: Handle<StandardMaterial>,
+    player_hit_mesh: Handle<Mesh>,
+    player_hit_material: Handle<StandardMaterial>,
+}
+
+/// Decals currently in the world, oldest first
+#[derive(Resource, Default)]
+struct DecalPool(VecDeque<Entity>);
+
+fn setup_impact_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(ImpactAssets {
+        decal_mesh: meshes.add(Plane3d::new(Vec3::Y, Vec2::splat(DECAL_SIZE / 2.0)).mesh()),
+        decal_material: materials.add(StandardMaterial {
+            base_color: Color::srgba(0.05, 0.05, 0.05, 0.9),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+        player_hit_mesh: meshes.add(Sphere { radius: 0.1 }.mesh().uv(8, 4)),
+        player_hit_material: materials.add(StandardMaterial {
+            base_color: Color::srgb(0.7, 0.0, 0.0),
+            unlit: true,
+            ..default()
+        }),
+    });
+}
+
+fn spawn_impact_effects(
+    mut impacts: EventReader<ImpactEvent>,
+    impact_assets: Res<ImpactAssets>,
+    mut decal_pool: ResMut<DecalPool>,
+    transforms: Query<&GlobalTransform>,
+    console: Res<DeveloperConsole>,
+    mut commands: Commands,
+) {
+    let max_decals = console.get_value::<u32>("r_max_decals").unwrap_or(0) as usize;
+
+    for impact in impacts.read() {
+        match impact.kind {
+            ImpactKind::World => {
+                let rotation = Quat::from_rotation_arc(Vec3::Y, impact.normal.try_normalize().unwrap_or(Vec3::Y));
+                let mut transform = Transform::from_translation(impact.position + impact.normal * DECAL_OFFSET)
+                    .with_rotation(rotation);
+                // Stuck to whatever was hit so it moves with it and is despawned along with it
+                let parent = impact.entity.and_then(|entity| Some((entity, transforms.get(entity).ok()?)));
+                if let Some((_, parent_transform)) = parent {
+                    transform = GlobalTransform::from(transform).reparented_to(parent_transform);
+                }
+
+                let decal = commands.spawn(PbrBundle {
+                    mesh: impact_assets.decal_mesh.clone(),
+                    material: impact_assets.decal_material.clone(),
+                    transform,
+                    ..default()
+                }).id();
+                if let Some((parent, _)) = parent {
+                    commands.entity(parent).add_child(decal);
+                }
+                decal_pool.0.push_back(decal);
+            }
+            ImpactKind::Player => {
+                commands.spawn((
+                    PbrBundle {
+                        mesh: impact_assets.player_hit_mesh.clone(),
+                        material: impact_assets.player_hit_material.clone(),
+                        transform: Transform::from_translation(impact.position),
+                        ..default()
+                    },
+                    Lifetime(Timer::from_seconds(PLAYER_HIT_EFFECT_LIFETIME, TimerMode::Once)),
+                ));
+            }
+        }
+    }
+
+    // The oldest decals make way for new ones
+    while decal_pool.0.len() > max_decals {
+        let Some(oldest) = decal_pool.0.pop_front() else {
+            break;
+        };
+        if let Some(decal) = commands.get_entity(oldest) {
+            decal.despawn_recursive();
+        }
+    }
+}
+
+fn receive_impacts(
+    mut messages: EventReader<ServerMessage>,
+    mut impact_events: EventWriter<ImpactEvent>,
+    rapier_context: Res<RapierContext>,
+) {
+    for message in messages.read() {
+        if let ServerMessage::Shot { shot, .. } = message {
+            impact_events.send_batch(shot.impacts.iter().map(|impact| {
+                let mut impact = ImpactEvent::from(impact);
+                // Find what the shot hit on our end by feeling for the surface it landed on
+                if impact.kind == ImpactKind::World {
+                    impact.entity = rapier_context.cast_ray(
+                        impact.position + impact.normal * SURFACE_PROBE_DISTANCE,
+                        -impact.normal,
+                        SURFACE_PROBE_DISTANCE * 2.0,
+                        true,
+                        QueryFilter::default().exclude_sensors(),
+                    ).map(|(entity, _)| entity);
+                }
+                impact
+            }));
+        }
+    }
+}
+
+pub fn impact_plugin(app: &mut App) {
+    app.add_event::<ImpactEvent>()
+        .init_resource::<DecalPool>()
+        .add_cvar("r_max_decals", 64u32)
+        .add_systems(Startup, setup_impact_assets)
+        .add_systems(Update, (receive_impacts, spawn_impact_effects).chain());
+}
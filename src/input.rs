@@ -0,0 +1,391 @@
+use std::fmt::Write as _;
+use std::fs;
+use bevy::ecs::system::SystemParam;
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use crate::dev_console::{AddConsoleCommand, ConsoleCommandResult};
+
+const BINDINGS_PATH: &str = "bindings.cfg";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputAction {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Crouch,
+    Fire,
+    Reload,
+    Melee,
+    WeaponSlot1,
+    WeaponSlot2,
+    WeaponSlot3,
+    WeaponSlot4,
+    WeaponSlot5,
+    NextWeapon,
+    PreviousWeapon,
+    ToggleConsole,
+    ToggleCursor,
+}
+
+const ACTION_NAMES: &[(&str, InputAction)] = &[
+    ("move_forward", InputAction::MoveForward),
+    ("move_back", InputAction::MoveBack),
+    ("move_left", InputAction::MoveLeft),
+    ("move_right", InputAction::MoveRight),
+    ("jump", InputAction::Jump),
+    ("crouch", InputAction::Crouch),
+    ("fire", InputAction::Fire),
+    ("reload", InputAction::Reload),
+    ("melee", InputAction::Melee),
+    ("weapon_slot_1", InputAction::WeaponSlot1),
+    ("weapon_slot_2", InputAction::WeaponSlot2),
+    ("weapon_slot_3", InputAction::WeaponSlot3),
+    ("weapon_slot_4", InputAction::WeaponSlot4),
+    ("weapon_slot_5", InputAction::WeaponSlot5),
+    ("next_weapon", InputAction::NextWeapon),
+    ("previous_weapon", InputAction::PreviousWeapon),
+    ("toggle_console", InputAction::ToggleConsole),
+    ("toggle_cursor", InputAction::ToggleCursor),
+];
+
+impl InputAction {
+    pub fn parse(source: &str) -> Option<Self> {
+        ACTION_NAMES.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(source))
+            .map(|(_, action)| *action)
+    }
+
+    pub fn name(&self) -> &'static str {
+        ACTION_NAMES.iter()
+            .find(|(_, action)| action == self)
+            .map(|(name, _)| *name)
+            .unwrap_or("unknown")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Wheel(WheelDirection),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WheelDirection {
+    Up,
+    Down,
+}
+
+macro_rules! named_keys {
+    ($($key:ident),* $(,)?) => {
+        const KEY_NAMES: &[(&str, KeyCode)] = &[$((stringify!($key), KeyCode::$key)),*];
+    };
+}
+
+named_keys!(
+    KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ, KeyK, KeyL, KeyM,
+    KeyN, KeyO, KeyP, KeyQ, KeyR, KeyS, KeyT, KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ,
+    Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Space, Escape, Backquote, Tab, Enter, Backspace, CapsLock,
+    ShiftLeft, ShiftRight, ControlLeft, ControlRight, AltLeft, AltRight,
+    ArrowUp, ArrowDown, ArrowLeft, ArrowRight,
+    Minus, Equal, BracketLeft, BracketRight, Backslash, Semicolon, Quote, Comma, Period, Slash,
+);
+
+const MOUSE_NAMES: &[(&str, MouseButton)] = &[
+    ("MouseLeft", MouseButton::Left),
+    ("MouseRight", MouseButton::Right),
+    ("MouseMiddle", MouseButton::Middle),
+    ("MouseBack", MouseButton::Back),
+    ("MouseForward", MouseButton::Forward),
+];
+
+const WHEEL_NAMES: &[(&str, WheelDirection)] = &[
+    ("MouseWheelUp", WheelDirection::Up),
+    ("MouseWheelDown", WheelDirection::Down),
+];
+
+impl InputBinding {
+    pub fn parse(source: &str) -> Option<Self> {
+        if let Some((_, key)) = KEY_NAMES.iter().find(|(name, _)| name.eq_ignore_ascii_case(source)) {
+            return Some(InputBinding::Key(*key));
+        }
+
+        if let Some((_, button)) = MOUSE_NAMES.iter().find(|(name, _)| name.eq_ignore_ascii_case(source)) {
+            return Some(InputBinding::Mouse(*button));
+        }
+
+        WHEEL_NAMES.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(source))
+            .map(|(_, direction)| InputBinding::Wheel(*direction))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputBinding::Key(key) => KEY_NAMES.iter()
+                .find(|(_, named_key)| named_key == key)
+                .map(|(name, _)| *name),
+            InputBinding::Mouse(button) => MOUSE_NAMES.iter()
+                .find(|(_, named_button)| named_button == button)
+                .map(|(name, _)| *name),
+            InputBinding::Wheel(direction) => WHEEL_NAMES.iter()
+                .find(|(_, named_direction)| named_direction == direction)
+                .map(|(name, _)| *name),
+        }.unwrap_or("unknown")
+    }
+}
+
+#[derive(Resource)]
+pub struct InputBindings {
+    bindings: HashMap<InputBinding, InputAction>,
+    /// Where changes are saved to, bindings without one only last until the game closes
+    path: Option<&'static str>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        let mut bindings = HashMap::new();
+        bindings.insert(InputBinding::Key(KeyCode::KeyW), InputAction::MoveForward);
+        bindings.insert(InputBinding::Key(KeyCode::KeyS), InputAction::MoveBack);
+        bindings.insert(InputBinding::Key(KeyCode::KeyA), InputAction::MoveLeft);
+        bindings.insert(InputBinding::Key(KeyCode::KeyD), InputAction::MoveRight);
+        bindings.insert(InputBinding::Key(KeyCode::Space), InputAction::Jump);
+        bindings.insert(InputBinding::Key(KeyCode::ControlLeft), InputAction::Crouch);
+        bindings.insert(InputBinding::Mouse(MouseButton::Left), InputAction::Fire);
+        bindings.insert(InputBinding::Key(KeyCode::KeyR), InputAction::Reload);
+        bindings.insert(InputBinding::Key(KeyCode::KeyV), InputAction::Melee);
+        bindings.insert(InputBinding::Key(KeyCode::Digit1), InputAction::WeaponSlot1);
+        bindings.insert(InputBinding::Key(KeyCode::Digit2), InputAction::WeaponSlot2);
+        bindings.insert(InputBinding::Key(KeyCode::Digit3), InputAction::WeaponSlot3);
+        bindings.insert(InputBinding::Key(KeyCode::Digit4), InputAction::WeaponSlot4);
+        bindings.insert(InputBinding::Key(KeyCode::Digit5), InputAction::WeaponSlot5);
+        bindings.insert(InputBinding::Wheel(WheelDirection::Up), InputAction::NextWeapon);
+        bindings.insert(InputBinding::Wheel(WheelDirection::Down), InputAction::PreviousWeapon);
+        bindings.insert(InputBinding::Key(KeyCode::Backquote), InputAction::ToggleConsole);
+        bindings.insert(InputBinding::Key(KeyCode::Escape), InputAction::ToggleCursor);
+        Self { bindings, path: None }
+    }
+}
+
+impl InputBindings {
+    pub fn bind(&mut self, binding: InputBinding, action: InputAction) {
+        self.bindings.insert(binding, action);
+    }
+
+    pub fn unbind(&mut self, binding: InputBinding) -> Option<InputAction> {
+        self.bindings.remove(&binding)
+    }
+
+    pub fn bindings_for(&self, action: InputAction) -> impl Iterator<Item = InputBinding> + '_ {
+        self.bindings.iter()
+            .filter(move |(_, bound_action)| **bound_action == action)
+            .map(|(binding, _)| *binding)
+    }
+
+    /// Bindings are persisted in the same `bind <input> <action>` form the console accepts
+    pub fn to_config(&self) -> String {
+        let mut lines = self.bindings.iter()
+            .map(|(binding, action)| format!("bind {} {}", binding.name(), action.name()))
+            .collect::<Vec<String>>();
+        lines.sort();
+
+        let mut config = String::new();
+        for line in lines {
+            let _ = writeln!(config, "{line}");
+        }
+        config
+    }
+
+    pub fn from_config(source: &str) -> Result<Self, String> {
+        let mut bindings = HashMap::new();
+        for line in source.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let tokens = line.split_whitespace().collect::<Vec<&str>>();
+            let &["bind", binding, action] = &tokens[..] else {
+                return Err(format!("Malformed binding: {line}"));
+            };
+            let binding = InputBinding::parse(binding).ok_or(format!("Unknown input {binding}"))?;
+            let action = InputAction::parse(action).ok_or(format!("Unknown action {action}"))?;
+            bindings.insert(binding, action);
+        }
+
+        Ok(Self { bindings, path: None })
+    }
+
+    pub fn load_or_default() -> Self {
+        let bindings = match fs::read_to_string(BINDINGS_PATH) {
+            Ok(source) => Self::from_config(&source).unwrap_or_else(|err| {
+                error!("Could not load bindings from {BINDINGS_PATH}: {err}");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+
+        Self { path: Some(BINDINGS_PATH), ..bindings }
+    }
+
+    pub fn save(&self) {
+        let Some(path) = self.path else {
+            return;
+        };
+        if let Err(err) = fs::write(path, self.to_config()) {
+            error!("Could not save bindings to {path}: {err}");
+        }
+    }
+}
+
+// The wheel has no held state, so a scroll counts as a press that is released again the same frame
+#[derive(Resource, Default)]
+struct MouseWheelInput {
+    up: bool,
+    down: bool,
+}
+
+impl MouseWheelInput {
+    fn scrolled(&self, direction: WheelDirection) -> bool {
+        match direction {
+            WheelDirection::Up => self.up,
+            WheelDirection::Down => self.down,
+        }
+    }
+}
+
+fn read_mouse_wheel(
+    mut wheel_events: EventReader<MouseWheel>,
+    mut mouse_wheel_input: ResMut<MouseWheelInput>,
+) {
+    *mouse_wheel_input = MouseWheelInput::default();
+    for event in wheel_events.read() {
+        if event.y > 0.0 {
+            mouse_wheel_input.up = true;
+        } else if event.y < 0.0 {
+            mouse_wheel_input.down = true;
+        }
+    }
+}
+
+/// Reads the state of input actions, whatever they are currently bound to
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    bindings: Res<'w, InputBindings>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse_buttons: Res<'w, ButtonInput<MouseButton>>,
+    mouse_wheel: Res<'w, MouseWheelInput>,
+}
+
+impl ActionInput<'_> {
+    fn check(&self, action: InputAction, key_check: impl Fn(&ButtonInput<KeyCode>, KeyCode) -> bool, mouse_check: impl Fn(&ButtonInput<MouseButton>, MouseButton) -> bool) -> bool {
+        self.bindings.bindings_for(action).any(|binding| match binding {
+            InputBinding::Key(key) => key_check(&self.keys, key),
+            InputBinding::Mouse(button) => mouse_check(&self.mouse_buttons, button),
+            InputBinding::Wheel(direction) => self.mouse_wheel.scrolled(direction),
+        })
+    }
+
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.check(action, |keys, key| keys.pressed(key), |buttons, button| buttons.pressed(button))
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.check(action, |keys, key| keys.just_pressed(key), |buttons, button| buttons.just_pressed(button))
+    }
+
+    pub fn just_released(&self, action: InputAction) -> bool {
+        self.check(action, |keys, key| keys.just_released(key), |buttons, button| buttons.just_released(button))
+    }
+}
+
+fn bind_command(
+    In(args): In<Vec<String>>,
+    mut bindings: ResMut<InputBindings>,
+) -> ConsoleCommandResult {
+    let [binding, action] = &args[..] else {
+        return Err("Usage: bind <input> <action>".into());
+    };
+    let binding = InputBinding::parse(binding).ok_or(format!("Unknown input {binding}"))?;
+    let action = InputAction::parse(action).ok_or(format!("Unknown action {action}"))?;
+
+    bindings.bind(binding, action);
+    bindings.save();
+    Ok(format!("{} bound to {}", binding.name(), action.name()))
+}
+
+fn unbind_command(
+    In(args): In<Vec<String>>,
+    mut bindings: ResMut<InputBindings>,
+) -> ConsoleCommandResult {
+    let [binding] = &args[..] else {
+        return Err("Usage: unbind <input>".into());
+    };
+    let binding = InputBinding::parse(binding).ok_or(format!("Unknown input {binding}"))?;
+
+    bindings.unbind(binding).ok_or(format!("{} is not bound", binding.name()))?;
+    bindings.save();
+    Ok(format!("{} unbound", binding.name()))
+}
+
+fn bindlist_command(
+    In(_): In<Vec<String>>,
+    bindings: Res<InputBindings>,
+) -> ConsoleCommandResult {
+    Ok(bindings.to_config())
+}
+
+pub fn input_plugin(app: &mut App) {
+    app.insert_resource(InputBindings::load_or_default())
+        .insert_resource(MouseWheelInput::default())
+        .add_systems(PreUpdate, read_mouse_wheel)
+        .add_console_command("bind", bind_command)
+        .add_console_command("unbind", unbind_command)
+        .add_console_command("bindlist", bindlist_command);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dev_console::{developer_console_plugin, run_console_command};
+
+    fn input_app() -> App {
+        let mut app = App::new();
+        // Left without a path, so the tests don't write over anyone's bindings.cfg
+        app.add_plugins(developer_console_plugin)
+            .insert_resource(InputBindings::default())
+            .add_console_command("bind", bind_command)
+            .add_console_command("unbind", unbind_command);
+        app.update();
+        app
+    }
+
+    #[test]
+    fn config_round_trips() {
+        let mut bindings = InputBindings::default();
+        bindings.bind(InputBinding::Mouse(MouseButton::Right), InputAction::Melee);
+        bindings.unbind(InputBinding::Key(KeyCode::KeyV));
+
+        let loaded = InputBindings::from_config(&bindings.to_config()).unwrap();
+        assert_eq!(loaded.bindings, bindings.bindings);
+    }
+
+    #[test]
+    fn config_rejects_lines_that_are_not_binds() {
+        assert_eq!(
+            InputBindings::from_config("unbind KeyW MoveForward").err(),
+            Some("Malformed binding: unbind KeyW MoveForward".to_string()),
+        );
+        assert!(InputBindings::from_config("bind KeyW").is_err());
+    }
+
+    #[test]
+    fn bind_and_unbind_report_what_they_did() {
+        let mut app = input_app();
+
+        assert_eq!(run_console_command(app.world_mut(), "bind KeyG reload"), Ok("KeyG bound to reload".to_string()));
+        assert_eq!(run_console_command(app.world_mut(), "unbind KeyG"), Ok("KeyG unbound".to_string()));
+        assert_eq!(run_console_command(app.world_mut(), "unbind KeyG"), Err("KeyG is not bound".to_string()));
+        assert_eq!(run_console_command(app.world_mut(), "bind KeyG"), Err("Usage: bind <input> <action>".to_string()));
+        assert_eq!(run_console_command(app.world_mut(), "bind KeyG dance"), Err("Unknown action dance".to_string()));
+    }
+}
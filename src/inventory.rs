@@ -0,0 +1,310 @@
+use bevy::prelude::*;
+use crate::dev_console::{AddConsoleCommand, ConsoleCommandResult};
+use crate::health::DeathEvent;
+use crate::input::{ActionInput, InputAction};
+use crate::lifetime::Lifetime;
+use crate::pickup::{Pickup, PickupTouched};
+use crate::player::Player;
+use crate::respawn::Dead;
+use crate::weapon::{Weapon, WeaponDefinition};
+
+pub const WEAPON_SLOTS: usize = 5;
+const DEFAULT_WEAPON: &str = "rifle";
+const DROPPED_WEAPON_LIFETIME: f32 = 30.0;
+
+pub fn weapon_path(weapon: &str) -> String {
+    format!("weapons/{weapon}.weapon.ron")
+}
+
+/// The other way round from `weapon_path`, for definitions that were loaded from a file
+pub fn weapon_name(definition: &Handle<WeaponDefinition>) -> Option<String> {
+    let path = definition.path()?;
+    let file_name = path.path().file_name()?.to_str()?;
+    file_name.strip_suffix(".weapon.ron").map(str::to_string)
+}
+
+enum WeaponSwitch {
+    Holstering { target: usize, timer: Timer },
+    Drawing { timer: Timer },
+}
+
+#[derive(Component, Default)]
+pub struct WeaponInventory {
+    slots: [Option<Weapon>; WEAPON_SLOTS],
+    active: Option<usize>,
+    switch: Option<WeaponSwitch>,
+}
+
+impl WeaponInventory {
+    pub fn active_weapon(&self) -> Option<&Weapon> {
+        self.active.and_then(|slot| self.slots[slot].as_ref())
+    }
+
+    pub fn active_weapon_mut(&mut self) -> Option<&mut Weapon> {
+        self.active.and_then(|slot| self.slots[slot].as_mut())
+    }
+
+    pub fn is_switching(&self) -> bool {
+        self.switch.is_some()
+    }
+
+    /// Puts the weapon in its slot, replacing whatever else was there. Picking up a weapon
+    /// that's already held only takes its ammo
+    pub fn give(&mut self, weapon: Weapon, definition: &WeaponDefinition) {
+        let slot = definition.slot.clamp(1, WEAPON_SLOTS) - 1;
+        if let Some(held) = self.slots[slot].as_mut() {
+            if held.definition == weapon.definition {
+                held.add_reserve(weapon.magazine() + weapon.reserve());
+                return;
+            }
+        }
+
+        self.slots[slot] = Some(weapon);
+        if self.active.is_none() {
+            self.active = Some(slot);
+            self.switch = Some(WeaponSwitch::Drawing { timer: Timer::from_seconds(definition.draw_time, TimerMode::Once) });
+        }
+    }
+
+    /// Starts putting away the held weapon to draw the one in `slot`
+    pub fn select(&mut self, slot: usize, definitions: &Assets<WeaponDefinition>) {
+        let current_target = match &self.switch {
+            Some(WeaponSwitch::Holstering { target, .. }) => Some(*target),
+            _ => self.active,
+        };
+        if slot >= WEAPON_SLOTS || self.slots[slot].is_none() || current_target == Some(slot) {
+            return;
+        }
+
+        let holster_time = self.active_weapon_mut()
+            .map(|weapon| {
+                weapon.cancel_reload();
+                definitions.get(&weapon.definition).map_or(0.0, |definition| definition.holster_time)
+            })
+            .unwrap_or(0.0);
+        self.switch = Some(WeaponSwitch::Holstering { target: slot, timer: Timer::from_seconds(holster_time, TimerMode::Once) });
+    }
+
+    /// Selects the next held weapon along from the current one, wrapping around
+    pub fn cycle(&mut self, forwards: bool, definitions: &Assets<WeaponDefinition>) {
+        let current = match &self.switch {
+            Some(WeaponSwitch::Holstering { target, .. }) => *target,
+            _ => self.active.unwrap_or(0),
+        };
+
+        let next = (1..WEAPON_SLOTS)
+            .map(|offset| if forwards { (current + offset) % WEAPON_SLOTS } else { (current + WEAPON_SLOTS - offset) % WEAPON_SLOTS })
+            .find(|slot| self.slots[*slot].is_some());
+        if let Some(next) = next {
+            self.select(next, definitions);
+        }
+    }
+
+    /// Removes the held weapon from the inventory, leaving the player empty handed
+    pub fn take_active(&mut self) -> Option<Weapon> {
+        self.switch = None;
+        self.active.take().and_then(|slot| self.slots[slot].take())
+    }
+
+    fn tick_switch(&mut self, delta: std::time::Duration, definitions: &Assets<WeaponDefinition>) {
+        match &mut self.switch {
+            Some(WeaponSwitch::Holstering { target, timer }) => {
+                if timer.tick(delta).finished() {
+                    self.active = Some(*target);
+                    let draw_time = self.active_weapon()
+                        .and_then(|weapon| definitions.get(&weapon.definition))
+                        .map_or(0.0, |definition| definition.draw_time);
+                    self.switch = Some(WeaponSwitch::Drawing { timer: Timer::from_seconds(draw_time, TimerMode::Once) });
+                }
+            }
+            Some(WeaponSwitch::Drawing { timer }) => {
+                if timer.tick(delta).finished() {
+                    self.switch = None;
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+fn equip_default_weapon(
+    players: Query<Entity, (With<Player>, Without<WeaponInventory>)>,
+    asset_server: Res<AssetServer>,
+    weapon_definitions: Res<Assets<WeaponDefinition>>,
+    mut commands: Commands,
+) {
+    let default_weapon = asset_server.load(weapon_path(DEFAULT_WEAPON));
+    let Some(definition) = weapon_definitions.get(&default_weapon) else {
+        return;
+    };
+
+    for player in players.iter() {
+        let mut inventory = WeaponInventory::default();
+        inventory.give(Weapon::new(default_weapon.clone(), definition), definition);
+        commands.entity(player).insert(inventory);
+    }
+}
+
+const SLOT_ACTIONS: [InputAction; WEAPON_SLOTS] = [
+    InputAction::WeaponSlot1,
+    InputAction::WeaponSlot2,
+    InputAction::WeaponSlot3,
+    InputAction::WeaponSlot4,
+    InputAction::WeaponSlot5,
+];
+
+pub fn handle_weapon_switching(
+    mut player_query: Query<&mut WeaponInventory, (With<Player>, Without<Dead>)>,
+    weapon_definitions: Res<Assets<WeaponDefinition>>,
+    actions: ActionInput,
+    time: Res<Time>,
+) {
+    let Ok(mut inventory) = player_query.get_single_mut() else {
+        return;
+    };
+
+    if let Some(slot) = SLOT_ACTIONS.iter().position(|action| actions.just_pressed(*action)) {
+        inventory.select(slot, &weapon_definitions);
+    } else if actions.just_pressed(InputAction::NextWeapon) {
+        inventory.cycle(true, &weapon_definitions);
+    } else if actions.just_pressed(InputAction::PreviousWeapon) {
+        inventory.cycle(false, &weapon_definitions);
+    }
+
+    inventory.tick_switch(time.delta(), &weapon_definitions);
+}
+
+#[derive(Component)]
+pub struct WeaponPickup {
+    definition: Handle<WeaponDefinition>,
+    /// Ammo left in a dropped weapon, pickups placed in the map come fully loaded
+    ammo: Option<(u32, u32)>,
+}
+
+#[derive(Resource)]
+pub struct WeaponPickupAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn setup_weapon_pickup_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(WeaponPickupAssets {
+        mesh: meshes.add(Cuboid::new(0.8, 0.2, 0.3)),
+        material: materials.add(Color::srgb(1.0, 0.8, 0.0)),
+    });
+}
+
+pub trait MakeEntityWeaponPickup {
+    /// Pickups that don't respawn are one-offs, gone once taken or after lying around for a while.
+    /// Without any ammo given the weapon comes fully loaded
+    fn make_weapon_pickup(&mut self, transform: Transform, definition: Handle<WeaponDefinition>, respawn: Option<f32>, ammo: Option<(u32, u32)>) -> &mut Self;
+}
+
+impl MakeEntityWeaponPickup for EntityWorldMut<'_> {
+    fn make_weapon_pickup(&mut self, transform: Transform, definition: Handle<WeaponDefinition>, respawn: Option<f32>, ammo: Option<(u32, u32)>) -> &mut Self {
+        let pickup_assets = self.world().resource::<WeaponPickupAssets>();
+        let (mesh, material) = (pickup_assets.mesh.clone(), pickup_assets.material.clone());
+
+        self.insert((
+            WeaponPickup { definition, ammo },
+            PbrBundle {
+                mesh,
+                material,
+                transform,
+                ..default()
+            },
+        ));
+        match respawn {
+            Some(respawn_time) => self.insert(Pickup::respawning(respawn_time)),
+            None => self.insert((
+                Pickup::once(),
+                Lifetime(Timer::from_seconds(DROPPED_WEAPON_LIFETIME, TimerMode::Once)),
+            )),
+        }
+    }
+}
+
+fn drop_weapon_on_death(
+    trigger: Trigger<DeathEvent>,
+    mut players: Query<(&mut WeaponInventory, &GlobalTransform)>,
+    mut commands: Commands,
+) {
+    let Ok((mut inventory, transform)) = players.get_mut(trigger.entity()) else {
+        return;
+    };
+    let Some(weapon) = inventory.take_active() else {
+        return;
+    };
+
+    let transform = Transform::from_translation(transform.translation());
+    let ammo = Some((weapon.magazine(), weapon.reserve()));
+    commands.spawn_empty().add(move |mut entity: EntityWorldMut| {
+        entity.make_weapon_pickup(transform, weapon.definition, None, ammo);
+    });
+}
+
+fn grant_weapon_pickups(
+    trigger: Trigger<PickupTouched>,
+    mut pickups: Query<(&WeaponPickup, &mut Pickup, &mut Visibility)>,
+    mut players: Query<&mut WeaponInventory>,
+    weapon_definitions: Res<Assets<WeaponDefinition>>,
+    mut commands: Commands,
+) {
+    let Ok((weapon_pickup, mut pickup, mut visibility)) = pickups.get_mut(trigger.entity()) else {
+        return;
+    };
+    let Ok(mut inventory) = players.get_mut(trigger.event().player) else {
+        return;
+    };
+    if !pickup.is_available() {
+        return;
+    }
+    let Some(definition) = weapon_definitions.get(&weapon_pickup.definition) else {
+        return;
+    };
+
+    let weapon = match weapon_pickup.ammo {
+        Some((magazine, reserve)) => Weapon::with_ammo(weapon_pickup.definition.clone(), magazine, reserve),
+        None => Weapon::new(weapon_pickup.definition.clone(), definition),
+    };
+    inventory.give(weapon, definition);
+    pickup.take(trigger.entity(), &mut visibility, &mut commands);
+}
+
+fn give_command(
+    In(args): In<Vec<String>>,
+    mut player_query: Query<&mut WeaponInventory, With<Player>>,
+    asset_server: Option<Res<AssetServer>>,
+    weapon_definitions: Option<Res<Assets<WeaponDefinition>>>,
+) -> ConsoleCommandResult {
+    let [weapon] = &args[..] else {
+        return Err("Usage: give <weapon>".into());
+    };
+    let mut inventory = player_query.get_single_mut().map_err(|_| "No player to give a weapon to".to_string())?;
+    // The headless server has the command without any assets to give
+    let (Some(asset_server), Some(weapon_definitions)) = (asset_server, weapon_definitions) else {
+        return Err("No weapons loaded".into());
+    };
+
+    let handle = asset_server.get_handle(weapon_path(weapon)).ok_or(format!("Unknown weapon {weapon}"))?;
+    let definition = weapon_definitions.get(&handle).ok_or(format!("{weapon} has not loaded yet"))?;
+    inventory.give(Weapon::new(handle.clone(), definition), definition);
+
+    Ok(format!("Gave {}", definition.name))
+}
+
+pub fn inventory_console_plugin(app: &mut App) {
+    app.add_cheat_command("give", give_command);
+}
+
+pub fn inventory_plugin(app: &mut App) {
+    app.add_plugins(inventory_console_plugin)
+        .add_systems(Startup, setup_weapon_pickup_assets)
+        .add_systems(Update, (equip_default_weapon, handle_weapon_switching).chain())
+        .observe(grant_weapon_pickups)
+        .observe(drop_weapon_on_death);
+}
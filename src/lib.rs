@@ -1,10 +1,28 @@
+pub mod breakable;
 pub mod cursor;
+pub mod damage_feedback;
+pub mod damage_modifiers;
 pub mod dev_console;
+pub mod dev_console_ui;
+pub mod explosion;
+pub mod flycam;
+pub mod hazard;
 pub mod health;
+pub mod hitbox;
+pub mod impact;
+pub mod input;
+pub mod inventory;
 pub mod lifetime;
+pub mod melee;
+pub mod pickup;
 
 pub mod player;
 pub mod player_ui;
+pub mod projectile;
+pub mod rcon;
+pub mod respawn;
+pub mod spectate;
 pub mod trenchbroom;
 pub mod config;
 pub mod netcode;
+pub mod weapon;
@@ -1,11 +1,15 @@
 use bevy::app::App;
 use bevy::prelude::{
-    Commands, Component, Deref, DerefMut, Entity, Query, Res, Time, Timer, Update,
+    Commands, Component, Deref, DerefMut, Entity, Event, Query, Res, Time, Timer, Update,
 };
 
 #[derive(Component, Deref, DerefMut)]
 pub struct Lifetime(pub Timer);
 
+/// Triggered on an entity just before it is despawned for running out of lifetime
+#[derive(Event)]
+pub struct LifetimeExpired;
+
 fn handle_lifetimes(
     mut lifetimes: Query<(&mut Lifetime, Entity)>,
     time: Res<Time>,
@@ -14,11 +18,13 @@ fn handle_lifetimes(
     for (mut lifetime, entity) in lifetimes.iter_mut() {
         lifetime.tick(time.delta());
         if lifetime.finished() {
+            commands.trigger_targets(LifetimeExpired, entity);
             commands.entity(entity).despawn();
         }
     }
 }
 
 pub fn lifetime_plugin(app: &mut App) {
-    app.add_systems(Update, handle_lifetimes);
+    app.add_event::<LifetimeExpired>()
+        .add_systems(Update, handle_lifetimes);
 }
@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+use crate::health::{DamageSource, DamageType, PotentialDamageEvent};
+use crate::hitbox::HitTargets;
+use crate::input::{ActionInput, InputAction};
+use crate::inventory::{handle_weapon_switching, WeaponInventory};
+use crate::player::{Player, PlayerCamera};
+use crate::respawn::Dead;
+use crate::weapon::WeaponDefinition;
+
+/// Quick melee stats, weapons without their own get the default bash
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct MeleeDefinition {
+    pub damage: i32,
+    /// How far in front of the camera the swing reaches
+    pub range: f32,
+    /// Radius of the ball swept out by the swing, so it doesn't need pinpoint aim
+    pub radius: f32,
+    /// Seconds between swings
+    pub cooldown: f32,
+    /// Applied when the victim is facing away from the attacker
+    pub backstab_multiplier: f32,
+}
+
+impl Default for MeleeDefinition {
+    fn default() -> Self {
+        Self {
+            damage: 40,
+            range: 1.5,
+            radius: 0.3,
+            cooldown: 0.8,
+            backstab_multiplier: 3.0,
+        }
+    }
+}
+
+// How far behind the victim the attacker has to be, as the cosine between where each of them is facing
+const BACKSTAB_THRESHOLD: f32 = 0.5;
+
+fn is_backstab(attack_direction: Vec3, victim_transform: &GlobalTransform) -> bool {
+    let victim_forward = victim_transform.forward().with_y(0.0).normalize_or_zero();
+    let attack_direction = attack_direction.with_y(0.0).normalize_or_zero();
+    victim_forward.dot(attack_direction) > BACKSTAB_THRESHOLD
+}
+
+#[allow(clippy::too_many_arguments)]
+fn quick_melee(
+    camera_query: Query<&GlobalTransform, With<PlayerCamera>>,
+    mut player_query: Query<(Entity, Option<&mut WeaponInventory>), (With<Player>, Without<Dead>)>,
+    targets: HitTargets,
+    transforms: Query<&GlobalTransform>,
+    weapon_definitions: Res<Assets<WeaponDefinition>>,
+    rapier_context: Res<RapierContext>,
+    actions: ActionInput,
+    time: Res<Time>,
+    mut cooldown: Local<f32>,
+    mut commands: Commands,
+) {
+    *cooldown = (*cooldown - time.delta_seconds()).max(0.0);
+
+    if *cooldown > 0.0 || !actions.just_pressed(InputAction::Melee) {
+        return;
+    }
+
+    let Ok(camera_transform) = camera_query.get_single() else {
+        error!("could not find camera");
+        return;
+    };
+    let Ok((player, inventory)) = player_query.get_single_mut() else {
+        return;
+    };
+
+    let mut melee = MeleeDefinition::default();
+    let mut source = DamageSource::Other("melee".into());
+    if let Some(mut inventory) = inventory {
+        if inventory.is_switching() {
+            return;
+        }
+        if let Some(weapon) = inventory.active_weapon_mut() {
+            // Swinging puts a stop to reloading, same as firing does
+            weapon.cancel_reload();
+            source = DamageSource::Weapon(weapon.definition.id());
+            if let Some(definition) = weapon_definitions.get(&weapon.definition) {
+                melee = definition.melee;
+            }
+        }
+    }
+    *cooldown = melee.cooldown;
+
+    let position = camera_transform.translation();
+    let direction = camera_transform.forward();
+    let hittable = |entity: Entity| targets.is_hittable(entity, player, &[]);
+    let query_filter = QueryFilter::default()
+        .exclude_collider(player)
+        .predicate(&hittable);
+
+    let Some((entity, _)) = rapier_context.cast_shape(
+        position,
+        Quat::IDENTITY,
+        *direction,
+        &Collider::ball(melee.radius),
+        ShapeCastOptions::with_max_time_of_impact(melee.range),
+        query_filter,
+    ) else {
+        debug!("Melee swing hit nothing");
+        return;
+    };
+
+    let hitbox = targets.hitbox(entity);
+    let victim = hitbox.map_or(entity, |hitbox| hitbox.owner);
+
+    let mut damage = melee.damage as f32;
+    if transforms.get(victim).is_ok_and(|transform| is_backstab(*direction, transform)) {
+        debug!("Backstab on {victim:?}");
+        damage *= melee.backstab_multiplier;
+    }
+
+    commands.trigger_targets(PotentialDamageEvent {
+        damage: damage.round() as i32,
+        originating_entity: player,
+        damage_type: DamageType::Melee,
+        source,
+        hit_region: hitbox.map(|hitbox| hitbox.region),
+        origin: Some(position),
+    }, victim);
+}
+
+pub fn melee_plugin(app: &mut App) {
+    app.add_systems(Update, quick_melee.after(handle_weapon_switching));
+}
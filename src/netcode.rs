@@ -1,27 +1,117 @@
 use std::net::{SocketAddr, UdpSocket};
 use std::time::{SystemTime, UNIX_EPOCH};
-use bevy::app::{App, FixedUpdate, Update};
-use bevy::prelude::{EventReader, ResMut};
+use bevy::app::{App, FixedUpdate, Startup, Update};
+use bevy::prelude::{AssetServer, Assets, Deref, Event, EventReader, EventWriter, IntoSystemConfigs, Local, Res, ResMut, Resource};
+use crate::dev_console::{AddConsoleVariable, ConsoleCommandResult, DeveloperConsole};
 use bevy_renet::renet::{ConnectionConfig, DefaultChannel, RenetClient, RenetServer, ServerEvent};
 use bevy::log::*;
 use bevy_renet::renet::transport::{ClientAuthentication, NetcodeClientTransport, NetcodeServerTransport, ServerAuthentication, ServerConfig};
 use bevy_renet::{RenetClientPlugin, RenetServerPlugin};
 use bevy_renet::transport::{NetcodeClientPlugin, NetcodeServerPlugin};
+use serde::{Deserialize, Serialize};
+use crate::inventory::weapon_path;
+use crate::weapon::{ReplicatedShot, WeaponDefinition};
+use crate::rcon::{rcon_client_plugin, rcon_server_plugin, RconRequest, RconRequests};
 
-fn send_server_message_system(mut server: ResMut<RenetServer>) {
-    let channel_id = 0;
-    server.broadcast_message(DefaultChannel::ReliableOrdered, "server message");
+/// Sent from a client to the server. Written as an event on the client, then sent on by the netcode
+#[derive(Event, Serialize, Deserialize, Debug, Clone)]
+pub enum ClientMessage {
+    ReloadStateChanged { reloading: bool },
+    /// A hitscan shot and everywhere it landed
+    Shot { shot: ReplicatedShot },
+    /// A breakable in the map was broken, by its id
+    PropBroken { id: u32 },
+    /// A console line to run on the server, as long as the password matches its `rcon_password`
+    Rcon { password: String, command: String },
+    /// Asks to have noclip toggled on a player, ourselves if no client id is given. Only done while the server has cheats on
+    ToggleNoclip { client_id: Option<u64> },
 }
 
-fn receive_server_message_system(mut server: ResMut<RenetServer>) {
+/// Sent from the server to clients. Written as an event on the server to broadcast it,
+/// and read as an event on the client once received
+#[derive(Event, Serialize, Deserialize, Debug, Clone)]
+pub enum ServerMessage {
+    ReloadStateChanged { client_id: u64, reloading: bool },
+    Shot { client_id: u64, shot: ReplicatedShot },
+    PropBroken { client_id: u64, id: u32 },
+    /// Sent only to the client whose RCON command it was
+    RconReply { result: ConsoleCommandResult },
+    /// The server's `sv_cheats`, sent whenever it changes and to clients as they connect
+    CheatsChanged { enabled: bool },
+    /// Every value registered with `add_server_cvar` on the server, sent whenever any of them changes and to clients as they connect
+    ServerValuesChanged { values: Vec<(String, String)> },
+    ToggleNoclip { client_id: u64 },
+    /// So everyone else can forget whatever they knew about that client
+    ClientDisconnected { client_id: u64 },
+}
+
+/// The id this client connected to the server with
+#[derive(Resource, Deref, Clone, Copy)]
+pub struct LocalClientId(pub u64);
+
+fn send_server_message_system(mut server: ResMut<RenetServer>, mut messages: EventReader<ServerMessage>) {
+    for message in messages.read() {
+        match bincode::serialize(message) {
+            Ok(bytes) => server.broadcast_message(DefaultChannel::ReliableOrdered, bytes),
+            Err(err) => error!("Could not serialize {message:?}: {err}"),
+        }
+    }
+}
+
+fn receive_server_message_system(
+    mut server: ResMut<RenetServer>,
+    mut rcon_requests: ResMut<RconRequests>,
+    console: Res<DeveloperConsole>,
+    asset_server: Res<AssetServer>,
+    weapon_definitions: Res<Assets<WeaponDefinition>>,
+    mut messages: EventWriter<ServerMessage>,
+) {
     for client_id in server.clients_id() {
-        while let Some(message) = server.receive_message(client_id, DefaultChannel::ReliableOrdered) {
-            info!("Received message {:?}", message);
+        while let Some(bytes) = server.receive_message(client_id, DefaultChannel::ReliableOrdered) {
+            let message = match bincode::deserialize::<ClientMessage>(&bytes) {
+                Ok(message) => message,
+                Err(err) => {
+                    error!("Received malformed message from client {client_id}: {err}");
+                    continue;
+                }
+            };
+
+            // Client state other players need to see is relayed on to everyone else
+            let relayed = match message {
+                ClientMessage::ReloadStateChanged { reloading } => ServerMessage::ReloadStateChanged { client_id: client_id.raw(), reloading },
+                // Shots that don't match what their weapon and seed allow were tampered with, and go no further
+                ClientMessage::Shot { shot } => {
+                    let definition = asset_server.get_handle(weapon_path(&shot.weapon))
+                        .and_then(|handle| weapon_definitions.get(&handle));
+                    if !definition.is_some_and(|definition| shot.impacts_match(client_id.raw(), definition)) {
+                        warn!("Dropped shot {} from client {client_id} with {} that doesn't match its spread", shot.shot, shot.weapon);
+                        continue;
+                    }
+                    ServerMessage::Shot { client_id: client_id.raw(), shot }
+                }
+                ClientMessage::PropBroken { id } => ServerMessage::PropBroken { client_id: client_id.raw(), id },
+                ClientMessage::Rcon { password, command } => {
+                    rcon_requests.push(RconRequest { client_id, password, command });
+                    continue;
+                }
+                ClientMessage::ToggleNoclip { client_id: target } => {
+                    if console.cheats_enabled() {
+                        messages.send(ServerMessage::ToggleNoclip { client_id: target.unwrap_or(client_id.raw()) });
+                    } else {
+                        warn!("Client {client_id} tried to noclip without sv_cheats");
+                    }
+                    continue;
+                }
+            };
+            match bincode::serialize(&relayed) {
+                Ok(bytes) => server.broadcast_message_except(client_id, DefaultChannel::ReliableOrdered, bytes),
+                Err(err) => error!("Could not serialize {relayed:?}: {err}"),
+            }
         }
     }
 }
 
-fn handle_server_events_system(mut server_events: EventReader<ServerEvent>) {
+fn handle_server_events_system(mut server_events: EventReader<ServerEvent>, mut messages: EventWriter<ServerMessage>) {
     for event in server_events.read() {
         match event {
             ServerEvent::ClientConnected {client_id} => {
@@ -29,33 +119,100 @@ fn handle_server_events_system(mut server_events: EventReader<ServerEvent>) {
             }
             ServerEvent::ClientDisconnected { client_id, reason } => {
                 info!("Client {client_id} disconnected: {reason}");
+                messages.send(ServerMessage::ClientDisconnected { client_id: client_id.raw() });
+            }
+        }
+    }
+}
+
+// Sent again to everyone whenever anyone connects, which keeps it simple and is rare enough not to matter
+fn broadcast_cheats_system(
+    console: Res<DeveloperConsole>,
+    mut server_events: EventReader<ServerEvent>,
+    mut sent: Local<Option<bool>>,
+    mut messages: EventWriter<ServerMessage>,
+) {
+    let enabled = console.cheats_enabled();
+    let connected = server_events.read().any(|event| matches!(event, ServerEvent::ClientConnected { .. }));
+
+    if connected || *sent != Some(enabled) {
+        *sent = Some(enabled);
+        messages.send(ServerMessage::CheatsChanged { enabled });
+    }
+}
+
+// Sent again to everyone whenever anyone connects, same as sv_cheats
+fn broadcast_server_values_system(
+    console: Res<DeveloperConsole>,
+    mut server_events: EventReader<ServerEvent>,
+    mut sent: Local<Option<Vec<(String, String)>>>,
+    mut messages: EventWriter<ServerMessage>,
+) {
+    let values = console.server_values();
+    let connected = server_events.read().any(|event| matches!(event, ServerEvent::ClientConnected { .. }));
+
+    if connected || sent.as_ref() != Some(&values) {
+        *sent = Some(values.clone());
+        messages.send(ServerMessage::ServerValuesChanged { values });
+    }
+}
+
+fn receive_server_values_system(mut messages: EventReader<ServerMessage>, mut console: ResMut<DeveloperConsole>) {
+    for message in messages.read() {
+        if let ServerMessage::ServerValuesChanged { values } = message {
+            for (key, value) in values {
+                console.set_server_value(key, value.clone());
             }
         }
     }
 }
 
-fn send_client_message_system(mut client: ResMut<RenetClient>) {
-    client.send_message(DefaultChannel::ReliableOrdered, "server message");
+fn run_console_as_server(mut console: ResMut<DeveloperConsole>) {
+    console.run_as_server();
 }
 
-fn receive_client_message_system(mut client: ResMut<RenetClient>) {
-    while let Some(message) = client.receive_message(DefaultChannel::ReliableOrdered) {
-        info!("Received message {:?}", message);
+fn receive_cheats_system(mut messages: EventReader<ServerMessage>, mut console: ResMut<DeveloperConsole>) {
+    for message in messages.read() {
+        if let ServerMessage::CheatsChanged { enabled } = message {
+            console.set_cheats_enabled(*enabled);
+        }
+    }
+}
+
+fn send_client_message_system(mut client: ResMut<RenetClient>, mut messages: EventReader<ClientMessage>) {
+    for message in messages.read() {
+        match bincode::serialize(message) {
+            Ok(bytes) => client.send_message(DefaultChannel::ReliableOrdered, bytes),
+            Err(err) => error!("Could not serialize {message:?}: {err}"),
+        }
+    }
+}
+
+fn receive_client_message_system(mut client: ResMut<RenetClient>, mut messages: EventWriter<ServerMessage>) {
+    while let Some(bytes) = client.receive_message(DefaultChannel::ReliableOrdered) {
+        match bincode::deserialize::<ServerMessage>(&bytes) {
+            Ok(message) => {
+                messages.send(message);
+            }
+            Err(err) => error!("Received malformed message from server: {err}"),
+        }
     }
 }
 
 pub fn client_plugin(app: &mut App) {
     let client = RenetClient::new(ConnectionConfig::default());
 
+    let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
+    // Unsecure connections pick their own id, the time we connected at is unique enough
+    let client_id = current_time.as_millis() as u64;
     let authentication = ClientAuthentication::Unsecure {
         server_addr: "127.0.0.1:5000".parse().unwrap(),
-        client_id: 0,
+        client_id,
         user_data: None,
         protocol_id: 0
     };
     let socket = UdpSocket::bind(("127.0.0.1", 0)).unwrap();
-    let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
-    let mut transport = NetcodeClientTransport::new(current_time, authentication, socket).unwrap();
+    let transport = NetcodeClientTransport::new(current_time, authentication, socket).unwrap();
 
 
     app.
@@ -63,8 +220,14 @@ pub fn client_plugin(app: &mut App) {
         .add_plugins(NetcodeClientPlugin)
         .insert_resource(client)
         .insert_resource(transport)
+        .insert_resource(LocalClientId(client_id))
+        .add_event::<ClientMessage>()
+        .add_event::<ServerMessage>()
+        .add_server_cvar("sv_cheats", false)
+        .add_plugins(rcon_client_plugin)
         .add_systems(FixedUpdate, send_client_message_system)
-        .add_systems(FixedUpdate, receive_client_message_system);
+        .add_systems(FixedUpdate, receive_client_message_system)
+        .add_systems(Update, (receive_cheats_system, receive_server_values_system));
 }
 
 pub fn server_plugin(app: &mut App) {
@@ -86,7 +249,13 @@ pub fn server_plugin(app: &mut App) {
         .add_plugins(NetcodeServerPlugin)
         .insert_resource(server)
         .insert_resource(transport)
+        .add_event::<ClientMessage>()
+        .add_event::<ServerMessage>()
+        .add_plugins(rcon_server_plugin)
+        .add_systems(Startup, run_console_as_server)
         .add_systems(FixedUpdate, receive_server_message_system)
-        .add_systems(FixedUpdate, handle_server_events_system)
+        .add_systems(FixedUpdate, handle_server_events_system.before(send_server_message_system))
+        .add_systems(FixedUpdate, broadcast_cheats_system.before(send_server_message_system))
+        .add_systems(FixedUpdate, broadcast_server_values_system.before(send_server_message_system))
         .add_systems(FixedUpdate, send_server_message_system);
 }
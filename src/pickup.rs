@@ -0,0 +1,235 @@
+use bevy::prelude::*;
+use crate::dev_console::DeveloperConsole;
+use crate::health::{Armor, HealEvent, Health};
+use crate::player::Player;
+use crate::respawn::Dead;
+
+pub const PICKUP_RADIUS: f32 = 1.0;
+
+/// Anything lying around for players to walk over and take. What it gives is up to the component of its kind
+#[derive(Component)]
+pub struct Pickup {
+    /// Pickups placed in the map come back after a while, one-off pickups are gone for good once taken
+    respawn: Option<Timer>,
+    available: bool,
+}
+
+impl Pickup {
+    pub fn respawning(respawn_time: f32) -> Self {
+        Self {
+            respawn: Some(Timer::from_seconds(respawn_time, TimerMode::Once)),
+            available: true,
+        }
+    }
+
+    pub fn once() -> Self {
+        Self {
+            respawn: None,
+            available: true,
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.available
+    }
+
+    /// Hides the pickup until it respawns, or gets rid of it if it never will
+    pub fn take(&mut self, entity: Entity, visibility: &mut Visibility, commands: &mut Commands) {
+        self.available = false;
+        match self.respawn.as_mut() {
+            Some(respawn) => {
+                respawn.reset();
+                *visibility = Visibility::Hidden;
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+}
+
+/// Triggered on a pickup for every player touching it. Observers for each kind of pickup hand over
+/// whatever it gives, then `take` it, leaving it for someone else if it would do nothing for them
+#[derive(Event)]
+pub struct PickupTouched {
+    pub player: Entity,
+}
+
+fn collect_pickups(
+    players: Query<(Entity, &GlobalTransform), (With<Player>, Without<Dead>)>,
+    pickups: Query<(Entity, &GlobalTransform, &Pickup)>,
+    mut commands: Commands,
+) {
+    for (pickup_entity, pickup_transform, pickup) in pickups.iter() {
+        if !pickup.available {
+            continue;
+        }
+
+        for (player, player_transform) in players.iter() {
+            if player_transform.translation().distance(pickup_transform.translation()) <= PICKUP_RADIUS {
+                commands.trigger_targets(PickupTouched { player }, pickup_entity);
+            }
+        }
+    }
+}
+
+fn respawn_pickups(
+    mut pickups: Query<(&mut Pickup, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    for (mut pickup, mut visibility) in pickups.iter_mut() {
+        if pickup.available {
+            continue;
+        }
+        let Some(respawn) = pickup.respawn.as_mut() else {
+            continue;
+        };
+
+        if respawn.tick(time.delta()).finished() {
+            pickup.available = true;
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ArmorPickup {
+    amount: i32,
+}
+
+#[derive(Resource)]
+pub struct ArmorPickupAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn setup_armor_pickup_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(ArmorPickupAssets {
+        mesh: meshes.add(Cuboid::new(0.5, 0.5, 0.2)),
+        material: materials.add(Color::srgb(0.1, 0.4, 1.0)),
+    });
+}
+
+pub trait MakeEntityArmorPickup {
+    fn make_armor_pickup(&mut self, transform: Transform, amount: i32, respawn_time: f32) -> &mut Self;
+}
+
+impl MakeEntityArmorPickup for EntityWorldMut<'_> {
+    fn make_armor_pickup(&mut self, transform: Transform, amount: i32, respawn_time: f32) -> &mut Self {
+        let pickup_assets = self.world().resource::<ArmorPickupAssets>();
+        let (mesh, material) = (pickup_assets.mesh.clone(), pickup_assets.material.clone());
+
+        self.insert((
+            ArmorPickup { amount },
+            Pickup::respawning(respawn_time),
+            PbrBundle {
+                mesh,
+                material,
+                transform,
+                ..default()
+            },
+        ))
+    }
+}
+
+fn grant_armor_pickups(
+    trigger: Trigger<PickupTouched>,
+    mut pickups: Query<(&ArmorPickup, &mut Pickup, &mut Visibility)>,
+    mut players: Query<&mut Armor>,
+    mut commands: Commands,
+) {
+    let Ok((armor_pickup, mut pickup, mut visibility)) = pickups.get_mut(trigger.entity()) else {
+        return;
+    };
+    let Ok(mut armor) = players.get_mut(trigger.event().player) else {
+        return;
+    };
+
+    // Players already wearing all the armor they can leave it for someone else
+    if !pickup.available || armor.current >= armor.max {
+        return;
+    }
+    armor.current = (armor.current + armor_pickup.amount).min(armor.max);
+    pickup.take(trigger.entity(), &mut visibility, &mut commands);
+}
+
+#[derive(Component)]
+pub struct HealthPickup {
+    amount: i32,
+    overheal: bool,
+}
+
+#[derive(Resource)]
+pub struct HealthPickupAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+    overheal_material: Handle<StandardMaterial>,
+}
+
+fn setup_health_pickup_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(HealthPickupAssets {
+        mesh: meshes.add(Cuboid::new(0.4, 0.4, 0.4)),
+        material: materials.add(Color::srgb(0.2, 1.0, 0.2)),
+        overheal_material: materials.add(Color::srgb(0.6, 1.0, 1.0)),
+    });
+}
+
+pub trait MakeEntityHealthPickup {
+    fn make_health_pickup(&mut self, transform: Transform, amount: i32, overheal: bool, respawn_time: f32) -> &mut Self;
+}
+
+impl MakeEntityHealthPickup for EntityWorldMut<'_> {
+    fn make_health_pickup(&mut self, transform: Transform, amount: i32, overheal: bool, respawn_time: f32) -> &mut Self {
+        let pickup_assets = self.world().resource::<HealthPickupAssets>();
+        let material = if overheal { &pickup_assets.overheal_material } else { &pickup_assets.material };
+        let (mesh, material) = (pickup_assets.mesh.clone(), material.clone());
+
+        self.insert((
+            HealthPickup { amount, overheal },
+            Pickup::respawning(respawn_time),
+            PbrBundle {
+                mesh,
+                material,
+                transform,
+                ..default()
+            },
+        ))
+    }
+}
+
+fn grant_health_pickups(
+    trigger: Trigger<PickupTouched>,
+    mut pickups: Query<(&HealthPickup, &mut Pickup, &mut Visibility)>,
+    players: Query<&Health>,
+    console: Res<DeveloperConsole>,
+    mut commands: Commands,
+) {
+    let Ok((health_pickup, mut pickup, mut visibility)) = pickups.get_mut(trigger.entity()) else {
+        return;
+    };
+    let player = trigger.event().player;
+    let Ok(health) = players.get(player) else {
+        return;
+    };
+
+    // Only taken by someone it would actually heal
+    if !pickup.available || health.current >= health.heal_cap(health_pickup.overheal, &console) {
+        return;
+    }
+    commands.trigger_targets(HealEvent { amount: health_pickup.amount, overheal: health_pickup.overheal }, player);
+    pickup.take(trigger.entity(), &mut visibility, &mut commands);
+}
+
+pub fn pickup_plugin(app: &mut App) {
+    app.add_event::<PickupTouched>()
+        .add_systems(Startup, (setup_armor_pickup_assets, setup_health_pickup_assets))
+        .add_systems(Update, (collect_pickups, respawn_pickups))
+        .observe(grant_armor_pickups)
+        .observe(grant_health_pickups);
+}
@@ -1,15 +1,14 @@
 use crate::cursor::CursorState;
-use crate::health::{DeathEvent, Health, PotentialDamageEvent};
-use crate::lifetime::Lifetime;
+use crate::health::{Armor, DamageSource, DamageType, Health, PotentialDamageEvent};
 use crate::{player_ui, config::GRAVITY};
-use bevy::color::palettes::basic::RED;
 use bevy::input::mouse::MouseMotion;
-use bevy::pbr::wireframe::{Wireframe, WireframeColor};
 use bevy::prelude::*;
 use bevy::window::CursorGrabMode;
 use bevy_rapier3d::prelude::*;
-use std::time::Duration;
-use crate::dev_console::{AddConsoleVariable, DeveloperConsole};
+use crate::dev_console::{AddConsoleCommand, ConsoleCommandResult, DeveloperConsole};
+use crate::netcode::{ClientMessage, LocalClientId, ServerMessage};
+use crate::input::{ActionInput, InputAction};
+use crate::respawn::Dead;
 
 #[derive(Component)]
 pub struct Player;
@@ -20,13 +19,30 @@ pub struct PlayerCamera;
 #[derive(Component)]
 pub struct Noclip;
 
+/// Velocity the player keeps between frames, on top of whatever they're moving with from input
+#[derive(Component, Default)]
+pub struct PlayerVelocity {
+    pub vertical: f32,
+    /// Horizontal velocity from knockback, dragged back down to nothing over time
+    pub knockback: Vec3,
+}
+
+impl PlayerVelocity {
+    pub fn apply_impulse(&mut self, impulse: Vec3) {
+        self.vertical += impulse.y;
+        self.knockback += Vec3::new(impulse.x, 0.0, impulse.z);
+    }
+}
+
 #[derive(Bundle)]
 pub struct FirstPersonPlayerBundle {
     controller_marker: Player,
     spatial: SpatialBundle,
     character_controller: KinematicCharacterController,
     collider: Collider,
-    health: Health
+    health: Health,
+    armor: Armor,
+    velocity: PlayerVelocity,
 }
 
 impl FirstPersonPlayerBundle {
@@ -66,6 +82,8 @@ impl Default for FirstPersonPlayerBundle {
                 ..default()
             },
             health: Health::with_max(100),
+            armor: Armor::with_max(100, 0.66),
+            velocity: PlayerVelocity::default(),
         }
     }
 }
@@ -94,6 +112,12 @@ pub struct PlayerData {
     speed: f32,
     sensitivity: f32,
     jump_speed: f32,
+    ground_knockback_drag: f32,
+    air_knockback_drag: f32,
+    /// Landing any faster than this hurts
+    fall_damage_speed: f32,
+    /// Damage for every unit of speed over `fall_damage_speed`
+    fall_damage_per_speed: f32,
 }
 
 impl Default for PlayerData {
@@ -102,6 +126,10 @@ impl Default for PlayerData {
             speed: 20.0,
             sensitivity: 0.1,
             jump_speed: 10.0,
+            ground_knockback_drag: 8.0,
+            air_knockback_drag: 0.5,
+            fall_damage_speed: 20.0,
+            fall_damage_per_speed: 4.0,
         }
     }
 }
@@ -109,11 +137,12 @@ impl Default for PlayerData {
 #[derive(Resource, Deref, DerefMut, Default)]
 struct MovementInput(Vec3);
 
+/// Yaw and pitch of the player's view, in degrees
 #[derive(Resource, Deref, DerefMut, Default)]
-struct LookInput(Vec2);
+pub struct LookInput(Vec2);
 
 fn handle_player_input(
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: ActionInput,
     mut movement_input: ResMut<MovementInput>,
     mut look_input: ResMut<LookInput>,
     mut mouse_events: EventReader<MouseMotion>,
@@ -126,25 +155,25 @@ fn handle_player_input(
 
     let mut intended_movement = Vec3::ZERO;
 
-    if keys.pressed(KeyCode::KeyA) {
+    if actions.pressed(InputAction::MoveLeft) {
         intended_movement.x -= 1.0
     }
-    if keys.pressed(KeyCode::KeyD) {
+    if actions.pressed(InputAction::MoveRight) {
         intended_movement.x += 1.0
     }
-    if keys.pressed(KeyCode::KeyW) {
+    if actions.pressed(InputAction::MoveForward) {
         intended_movement.z -= 1.0
     }
-    if keys.pressed(KeyCode::KeyS) {
+    if actions.pressed(InputAction::MoveBack) {
         intended_movement.z += 1.0
     }
 
     **movement_input = intended_movement.normalize_or_zero();
 
-    if keys.pressed(KeyCode::Space) {
+    if actions.pressed(InputAction::Jump) {
         movement_input.y = 1.0;
     }
-    if keys.pressed(KeyCode::ControlLeft) {
+    if actions.pressed(InputAction::Crouch) {
         movement_input.y = -1.0;
     }
 
@@ -158,7 +187,7 @@ fn handle_player_input(
 fn handle_noclip_movement(
     mut player_query: Query<
         &mut Transform,
-        With<Noclip>,
+        (With<Noclip>, Without<Dead>),
     >,
     camera_query: Query<&GlobalTransform, With<PlayerCamera>>,
     time: Res<Time>,
@@ -185,19 +214,21 @@ fn handle_noclip_movement(
 fn handle_normal_player_movement(
     mut player_query: Query<
         (
+            Entity,
             &Transform,
             &mut KinematicCharacterController,
+            &mut PlayerVelocity,
             Option<&KinematicCharacterControllerOutput>,
         ),
-        (With<Player>, Without<Noclip>)
+        (With<Player>, Without<Noclip>, Without<Dead>)
     >,
     time: Res<Time>,
     mut movement_input: ResMut<MovementInput>,
     mut grounded_timer: Local<f32>,
-    mut vertical_movement: Local<f32>,
     player_data: Res<PlayerData>,
+    mut commands: Commands,
 ) {
-    let Ok((transform, mut controller, output)) = player_query.get_single_mut() else {
+    let Ok((player, transform, mut controller, mut velocity, output)) = player_query.get_single_mut() else {
         return;
     };
 
@@ -208,27 +239,47 @@ fn handle_normal_player_movement(
     // Clear input
     **movement_input = Vec3::ZERO;
     // Check physics ground check
-    if output.map(|o| o.grounded).unwrap_or(false) {
+    let grounded = output.map(|o| o.grounded).unwrap_or(false);
+    if grounded {
         *grounded_timer = 0.5;
-        *vertical_movement = 0.0;
+        // Whatever speed we were still falling at is how hard we hit the ground
+        let landing_speed = -velocity.vertical;
+        if landing_speed > player_data.fall_damage_speed {
+            let damage = (landing_speed - player_data.fall_damage_speed) * player_data.fall_damage_per_speed;
+            commands.trigger_targets(PotentialDamageEvent {
+                originating_entity: player,
+                damage: damage.round() as i32,
+                damage_type: DamageType::Fall,
+                source: DamageSource::Hazard("fall".into()),
+                hit_region: None,
+                origin: None,
+            }, player);
+        }
+        // Only stop falling, an upwards knockback this frame should still launch us
+        velocity.vertical = velocity.vertical.max(0.0);
     }
     // If we are grounded we can jump
     if *grounded_timer > 0.0 {
         *grounded_timer -= delta_time;
         // If we jump we clear the grounded tolerance
         if jump_speed > 0.0 {
-            *vertical_movement = jump_speed;
+            velocity.vertical = jump_speed;
             *grounded_timer = 0.0;
         }
     }
-    movement.y = *vertical_movement;
-    *vertical_movement += GRAVITY * delta_time * controller.custom_mass.unwrap_or(1.0);
-    controller.translation = Some(transform.rotation * (movement * delta_time));
+    movement.y = velocity.vertical;
+    velocity.vertical += GRAVITY * delta_time * controller.custom_mass.unwrap_or(1.0);
+
+    let knockback_drag = if grounded { player_data.ground_knockback_drag } else { player_data.air_knockback_drag };
+    let knockback = velocity.knockback;
+    velocity.knockback *= (-knockback_drag * delta_time).exp();
+
+    controller.translation = Some(transform.rotation * (movement * delta_time) + knockback * delta_time);
 }
 
 fn handle_player_look(
-    mut player_query: Query<&mut Transform, With<Player>>,
-    mut camera_query: Query<&mut Transform, (With<Camera>, Without<Player>)>,
+    mut player_query: Query<&mut Transform, (With<Player>, Without<Dead>)>,
+    mut camera_query: Query<&mut Transform, (With<PlayerCamera>, Without<Player>)>,
     look_input: Res<LookInput>,
 ) {
     let Ok(mut transform) = player_query.get_single_mut() else {
@@ -241,104 +292,80 @@ fn handle_player_look(
     transform.rotation = Quat::from_axis_angle(Vec3::X, look_input.y.to_radians());
 }
 
-pub fn create_player_at_location(commands: &mut Commands, position: Vec3) {
+pub fn create_player(commands: &mut Commands, transform: Transform) {
     commands
-        .spawn(FirstPersonPlayerBundle::new(Transform::from_translation(position)))
+        .spawn(FirstPersonPlayerBundle::new(transform))
         .with_children(|b| {
             b.spawn(FirstPersonCameraBundle::default());
         });
 }
 
-fn shootmans(
-    camera_query: Query<&GlobalTransform, With<PlayerCamera>>,
-    player_query: Query<Entity, With<Player>>,
-    rapier_context: Res<RapierContext>,
-    keys: Res<ButtonInput<MouseButton>>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    let Ok(player_camera_transform) = camera_query.get_single() else {
-        error!("could not find camera");
-        return;
-    };
-
-    let Ok(player) = player_query.get_single() else {
-        error!("could not find player");
-        return;
-    };
+pub fn create_player_at_location(commands: &mut Commands, position: Vec3) {
+    create_player(commands, Transform::from_translation(position));
+}
 
-    if keys.just_pressed(MouseButton::Left) {
-        let ray_pos = player_camera_transform.translation();
-        let ray_direction = player_camera_transform.forward();
-        let max_toi = 500.0;
-        let solid = true;
-        let query_filter = QueryFilter {
-            flags: Default::default(),
-            groups: None,
-            exclude_collider: Some(player),
-            exclude_rigid_body: None,
-            predicate: None,
-        };
+fn set_noclip(commands: &mut Commands, player: Entity, noclip: bool) {
+    if noclip {
+        commands.entity(player).remove::<Collider>()
+            .insert(Noclip);
+    } else {
+        commands.entity(player).insert(Collider::round_cylinder(0.9, 0.3, 0.2))
+            .remove::<Noclip>();
+    }
+}
 
-        if let Some((entity, toi)) = rapier_context.cast_ray(
-            ray_pos,
-            ray_direction.as_vec3(),
-            max_toi,
-            solid,
-            query_filter,
-        ) {
-            let hit_point = ray_pos + (ray_direction * toi);
-            debug!("Hit entity '{:?}' at pos {}", entity, hit_point);
+// The server decides who gets to noclip, so clients ask it and the server's own console tells everyone
+fn noclip_command(
+    In(args): In<Vec<String>>,
+    local_client: Option<Res<LocalClientId>>,
+    mut client_messages: EventWriter<ClientMessage>,
+    mut server_messages: EventWriter<ServerMessage>,
+) -> ConsoleCommandResult {
+    let target = match args.first() {
+        Some(arg) => Some(arg.parse::<u64>().map_err(|_| format!("Not a client id: {arg}"))?),
+        None => None,
+    };
 
-            commands.trigger_targets(PotentialDamageEvent {
-                damage: 25,
-                originating_entity: player
-            }, entity);
-
-            #[cfg(debug_assertions)]
-            commands
-                .spawn(Lifetime(Timer::new(
-                    Duration::from_secs(2),
-                    TimerMode::Once,
-                )))
-                .insert(PbrBundle {
-                    mesh: meshes.add(Sphere { radius: 0.25 }.mesh().uv(8, 4)),
-                    material: materials.add(Color::NONE),
-                    transform: Transform::from_translation(hit_point),
-                    ..Default::default()
-                })
-                .insert(Wireframe)
-                .insert(WireframeColor { color: RED.into() });
-        }
+    if local_client.is_some() {
+        client_messages.send(ClientMessage::ToggleNoclip { client_id: target });
+        return Ok(String::new());
     }
+    let client_id = target.ok_or("Usage: noclip <client id>")?;
+    server_messages.send(ServerMessage::ToggleNoclip { client_id });
+    Ok(format!("Toggled noclip on client {client_id}"))
 }
 
-pub fn listen_for_death(
-    trigger: Trigger<DeathEvent>,
+fn receive_noclip(
+    mut messages: EventReader<ServerMessage>,
+    local_client: Res<LocalClientId>,
+    players: Query<(Entity, Has<Noclip>), With<Player>>,
     mut commands: Commands,
 ) {
-    commands.entity(trigger.entity()).despawn_recursive();
+    for message in messages.read() {
+        let ServerMessage::ToggleNoclip { client_id } = message else {
+            continue;
+        };
+        if *client_id != **local_client {
+            continue;
+        }
+
+        for (player, noclip) in players.iter() {
+            set_noclip(&mut commands, player, !noclip);
+        }
+    }
 }
 
-fn update_noclip(
-    mut last_noclip_value: Local<bool>,
+fn remove_noclip_without_cheats(
     console: Res<DeveloperConsole>,
-    player: Query<Entity, With<Player>>,
-    mut commands: Commands
+    players: Query<Entity, (With<Player>, With<Noclip>)>,
+    mut commands: Commands,
 ) {
-    if let Ok(noclip) = console.get_value::<bool>("noclip") {
-        if *last_noclip_value != noclip {
-            let entity = player.get_single().unwrap();
-            if noclip {
-                commands.entity(entity).remove::<Collider>()
-                    .insert(Noclip);
-            } else {
-                commands.entity(entity).insert(Collider::round_cylinder(0.9, 0.3, 0.2))
-                    .remove::<Noclip>();
-            }
-            *last_noclip_value = noclip;
-        }
+    if console.cheats_enabled() {
+        return;
+    }
+
+    for player in players.iter() {
+        set_noclip(&mut commands, player, false);
     }
 }
 
@@ -353,10 +380,13 @@ impl<'w> MakeEntityPlayer<'w> for EntityWorldMut<'w> {
             .with_children(|builder| {
                 builder.spawn(FirstPersonCameraBundle::default());
             })
-            .observe(listen_for_death)
     }
 }
 
+pub fn player_console_plugin(app: &mut App) {
+    app.add_cheat_command("noclip", noclip_command);
+}
+
 pub fn first_person_controller_plugin(app: &mut App) {
     app.insert_resource(PlayerData::default())
         .insert_resource(MovementInput::default())
@@ -367,8 +397,7 @@ pub fn first_person_controller_plugin(app: &mut App) {
             handle_noclip_movement
         ).after(handle_player_look))
         .add_systems(Update, handle_player_look)
-        .add_systems(Update, shootmans)
-        .add_cvar("noclip", false)
-        .add_systems(Update, update_noclip)
+        .add_plugins(player_console_plugin)
+        .add_systems(Update, (receive_noclip, remove_noclip_without_cheats))
         .add_plugins(player_ui::PlayerUiPlugin);
 }
@@ -1,5 +1,7 @@
-use crate::health::Health;
+use crate::health::{Armor, Health};
 use crate::player::Player;
+use crate::inventory::WeaponInventory;
+use crate::weapon::RemoteReloadStates;
 use bevy::color::Color;
 use bevy::hierarchy::BuildChildren;
 use bevy::prelude::*;
@@ -9,7 +11,7 @@ pub struct PlayerUiPlugin;
 impl Plugin for PlayerUiPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, spawn_player_ui)
-            .add_systems(Update, handle_player_health_bar);
+            .add_systems(Update, (handle_player_health_bar, handle_player_armor_text, handle_player_ammo_text, handle_remote_players_text));
     }
 }
 
@@ -19,6 +21,16 @@ pub struct PlayerHealthBar;
 #[derive(Component)]
 pub struct ActiveHealthBar;
 
+#[derive(Component)]
+pub struct PlayerArmorText;
+
+#[derive(Component)]
+pub struct PlayerAmmoText;
+
+/// Everyone else connected, along with whether they are reloading
+#[derive(Component)]
+pub struct RemotePlayersText;
+
 pub fn spawn_player_ui(mut commands: Commands) {
     let health_container = NodeBundle {
         style: Style {
@@ -74,6 +86,57 @@ pub fn spawn_player_ui(mut commands: Commands) {
     commands
         .entity(health_bar_container_entity)
         .push_children(&[active_health_bar_entity, health_text]);
+
+    let ammo_text = TextBundle::from_section(
+        "",
+        TextStyle {
+            font_size: 35.,
+            color: Color::WHITE,
+            ..default()
+        },
+    )
+    .with_text_justify(JustifyText::Left)
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        left: Val::Percent(42.),
+        bottom: Val::Percent(15.),
+        ..default()
+    });
+    commands.spawn(ammo_text).insert(PlayerAmmoText);
+
+    let armor_text = TextBundle::from_section(
+        "",
+        TextStyle {
+            font_size: 35.,
+            color: Color::srgb(0.4, 0.7, 1.),
+            ..default()
+        },
+    )
+    .with_text_justify(JustifyText::Left)
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        left: Val::Percent(15.),
+        bottom: Val::Percent(21.),
+        ..default()
+    });
+    commands.spawn(armor_text).insert(PlayerArmorText);
+
+    let remote_players_text = TextBundle::from_section(
+        "",
+        TextStyle {
+            font_size: 20.,
+            color: Color::WHITE,
+            ..default()
+        },
+    )
+    .with_text_justify(JustifyText::Right)
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        right: Val::Percent(2.),
+        top: Val::Percent(2.),
+        ..default()
+    });
+    commands.spawn(remote_players_text).insert(RemotePlayersText);
 }
 
 pub fn handle_player_health_bar(
@@ -95,7 +158,7 @@ pub fn handle_player_health_bar(
     for &child in container_children {
         if let Ok(mut active_health_bar) = style_query.get_mut(child) {
             active_health_bar.width =
-                Val::Percent(player_health.current as f32 / player_health.max as f32 * 100.);
+                Val::Percent((player_health.current as f32 / player_health.max as f32 * 100.).clamp(0., 100.));
         }
 
         if let Ok(mut text) = health_text.get_mut(child) {
@@ -103,3 +166,68 @@ pub fn handle_player_health_bar(
         }
     }
 }
+
+pub fn handle_player_armor_text(
+    player_query: Query<&Armor, With<Player>>,
+    mut armor_text_query: Query<&mut Text, With<PlayerArmorText>>,
+) {
+    let Ok(mut armor_text) = armor_text_query.get_single_mut() else {
+        error!("could not find player armor text!");
+        return;
+    };
+
+    // Nothing to show until some armor has been picked up
+    armor_text.sections[0].value = match player_query.get_single() {
+        Ok(armor) if armor.current > 0 => format!("armor {}", armor.current),
+        _ => String::new(),
+    };
+}
+
+pub fn handle_player_ammo_text(
+    player_query: Query<&WeaponInventory, With<Player>>,
+    mut ammo_text_query: Query<&mut Text, With<PlayerAmmoText>>,
+) {
+    let Ok(mut ammo_text) = ammo_text_query.get_single_mut() else {
+        error!("could not find player ammo text!");
+        return;
+    };
+
+    let Some(weapon) = player_query.get_single().ok().and_then(WeaponInventory::active_weapon) else {
+        ammo_text.sections[0].value.clear();
+        return;
+    };
+
+    ammo_text.sections[0].value = if weapon.is_reloading() {
+        format!("reloading... / {}", weapon.reserve())
+    } else {
+        format!("{} / {}", weapon.magazine(), weapon.reserve())
+    };
+    ammo_text.sections[0].style.color = if weapon.magazine() == 0 {
+        Color::srgb(1., 0., 0.)
+    } else {
+        Color::WHITE
+    };
+}
+
+pub fn handle_remote_players_text(
+    remote_reload_states: Res<RemoteReloadStates>,
+    mut remote_players_text_query: Query<&mut Text, With<RemotePlayersText>>,
+) {
+    if !remote_reload_states.is_changed() {
+        return;
+    }
+    let Ok(mut remote_players_text) = remote_players_text_query.get_single_mut() else {
+        error!("could not find remote players text!");
+        return;
+    };
+
+    let mut client_ids: Vec<_> = remote_reload_states.keys().copied().collect();
+    client_ids.sort();
+    remote_players_text.sections[0].value = client_ids.iter()
+        .map(|client_id| match remote_reload_states[client_id] {
+            true => format!("player {client_id} reloading..."),
+            false => format!("player {client_id}"),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+}
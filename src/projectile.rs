@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+use crate::explosion::{explosion_plugin, ExplosionEvent};
+use crate::health::DamageSource;
+use crate::hitbox::HitTargets;
+use crate::lifetime::{Lifetime, LifetimeExpired};
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct ProjectileDefinition {
+    pub speed: f32,
+    /// 0 flies dead straight like a rocket, 1 falls like a grenade
+    #[serde(default)]
+    pub gravity_scale: f32,
+    /// How much of its speed the projectile keeps when bouncing off something
+    #[serde(default)]
+    pub restitution: f32,
+    pub radius: f32,
+    /// Seconds until the projectile detonates by itself
+    pub fuse: f32,
+    pub detonate_on_impact: bool,
+    /// Damage falls off to nothing at this distance from the detonation
+    pub blast_radius: f32,
+    /// Impulse given to players at the centre of the blast
+    #[serde(default)]
+    pub knockback: f32,
+}
+
+#[derive(Component)]
+pub struct Projectile {
+    pub owner: Entity,
+    pub source: DamageSource,
+    pub damage: i32,
+    detonate_on_impact: bool,
+    blast_radius: f32,
+    knockback: f32,
+}
+
+#[derive(Resource)]
+pub struct ProjectileAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn setup_projectile_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(ProjectileAssets {
+        mesh: meshes.add(Sphere { radius: 1.0 }.mesh().uv(16, 8)),
+        material: materials.add(Color::srgb(0.2, 0.2, 0.2)),
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_projectile(
+    commands: &mut Commands,
+    projectile_assets: &ProjectileAssets,
+    definition: &ProjectileDefinition,
+    owner: Entity,
+    source: DamageSource,
+    damage: i32,
+    position: Vec3,
+    direction: Vec3,
+) {
+    commands.spawn((
+        Projectile {
+            owner,
+            source,
+            damage,
+            detonate_on_impact: definition.detonate_on_impact,
+            blast_radius: definition.blast_radius,
+            knockback: definition.knockback,
+        },
+        PbrBundle {
+            mesh: projectile_assets.mesh.clone(),
+            material: projectile_assets.material.clone(),
+            // Rapier scales colliders along with the transform, so the unit ball collider ends up the right size as well
+            transform: Transform::from_translation(position).with_scale(Vec3::splat(definition.radius)),
+            ..default()
+        },
+        RigidBody::Dynamic,
+        Collider::ball(1.0),
+        Velocity::linear(direction * definition.speed),
+        GravityScale(definition.gravity_scale),
+        Restitution::coefficient(definition.restitution),
+        Ccd::enabled(),
+        ActiveEvents::COLLISION_EVENTS,
+        Lifetime(Timer::from_seconds(definition.fuse, TimerMode::Once)),
+    ));
+}
+
+fn detonate(projectile: &Projectile, position: Vec3, commands: &mut Commands) {
+    debug!("Projectile detonated at {position}");
+
+    commands.trigger(ExplosionEvent {
+        originating_entity: projectile.owner,
+        source: projectile.source.clone(),
+        position,
+        radius: projectile.blast_radius,
+        damage: projectile.damage,
+        knockback: projectile.knockback,
+    });
+}
+
+fn detonate_on_impact(
+    mut collision_events: EventReader<CollisionEvent>,
+    projectiles: Query<(&Projectile, &Transform)>,
+    targets: HitTargets,
+    mut commands: Commands,
+) {
+    let mut detonated = Vec::new();
+
+    for event in collision_events.read() {
+        let CollisionEvent::Started(first, second, _) = *event else {
+            continue;
+        };
+
+        for (projectile_entity, other) in [(first, second), (second, first)] {
+            let Ok((projectile, transform)) = projectiles.get(projectile_entity) else {
+                continue;
+            };
+            // Flying through trigger volumes like hazards isn't an impact either
+            if targets.is_trigger_volume(other) {
+                continue;
+            }
+            // Brushing past the owner's own hitboxes on the way out of the barrel isn't an impact
+            let other = targets.hitbox(other).map_or(other, |hitbox| hitbox.owner);
+            if !projectile.detonate_on_impact || other == projectile.owner || detonated.contains(&projectile_entity) {
+                continue;
+            }
+
+            detonate(projectile, transform.translation, &mut commands);
+            commands.entity(projectile_entity).despawn();
+            detonated.push(projectile_entity);
+        }
+    }
+}
+
+// Projectiles that never hit anything worth detonating on go off once their fuse runs out
+fn detonate_on_fuse(
+    trigger: Trigger<LifetimeExpired>,
+    projectiles: Query<(&Projectile, &Transform)>,
+    mut commands: Commands,
+) {
+    if let Ok((projectile, transform)) = projectiles.get(trigger.entity()) {
+        detonate(projectile, transform.translation, &mut commands);
+    }
+}
+
+pub fn projectile_plugin(app: &mut App) {
+    app.add_plugins(explosion_plugin)
+        .add_systems(Startup, setup_projectile_assets)
+        .add_systems(Update, detonate_on_impact)
+        .observe(detonate_on_fuse);
+}
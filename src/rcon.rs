@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+use bevy_renet::renet::{ClientId, DefaultChannel, RenetServer};
+use crate::dev_console::{run_console_command, AddConsoleCommand, AddConsoleVariable, ConsoleCommandResult, DeveloperConsole};
+use crate::netcode::{ClientMessage, ServerMessage};
+
+/// A console line a client wants run on the server
+pub struct RconRequest {
+    pub client_id: ClientId,
+    pub password: String,
+    pub command: String,
+}
+
+/// Requests received by the netcode, waiting for the world to be free to run them
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct RconRequests(Vec<RconRequest>);
+
+fn rcon_command(
+    In(args): In<Vec<String>>,
+    console: Res<DeveloperConsole>,
+    mut messages: EventWriter<ClientMessage>,
+) -> ConsoleCommandResult {
+    if args.is_empty() {
+        return Err("Usage: rcon <command>".into());
+    }
+
+    let password = console.get_value::<String>("rcon_password").unwrap_or_default();
+    messages.send(ClientMessage::Rcon { password, command: args.join(" ") });
+    // Whatever the server has to say comes back as its own reply
+    Ok(String::new())
+}
+
+fn receive_rcon_replies(
+    mut messages: EventReader<ServerMessage>,
+    mut console: ResMut<DeveloperConsole>,
+) {
+    for message in messages.read() {
+        if let ServerMessage::RconReply { result } = message {
+            console.print_result(result.clone());
+        }
+    }
+}
+
+fn run_rcon_requests(world: &mut World) {
+    let requests = std::mem::take(&mut **world.resource_mut::<RconRequests>());
+
+    for request in requests {
+        let password = world.resource::<DeveloperConsole>().get_value::<String>("rcon_password").unwrap_or_default();
+        // Without a password set RCON is switched off entirely
+        let result = if password.is_empty() || request.password != password {
+            warn!("Refused RCON command from client {}", request.client_id);
+            Err("Bad RCON password".to_string())
+        } else {
+            info!("RCON command from client {}: {}", request.client_id, request.command);
+            let result = run_console_command(world, &request.command);
+            world.resource_mut::<DeveloperConsole>().print_result(result.clone());
+            result
+        };
+
+        // Only the client that asked gets the reply
+        match bincode::serialize(&ServerMessage::RconReply { result }) {
+            Ok(bytes) => world.resource_mut::<RenetServer>().send_message(request.client_id, DefaultChannel::ReliableOrdered, bytes),
+            Err(err) => error!("Could not serialize RCON reply: {err}"),
+        }
+    }
+}
+
+pub fn rcon_client_plugin(app: &mut App) {
+    app.add_cvar("rcon_password", String::new())
+        .add_console_command("rcon", rcon_command)
+        .add_systems(Update, receive_rcon_replies);
+}
+
+pub fn rcon_server_plugin(app: &mut App) {
+    app.init_resource::<RconRequests>()
+        .add_cvar("rcon_password", String::new())
+        .add_systems(Update, run_rcon_requests);
+}
@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::dev_console::{AddConsoleVariable, DeveloperConsole};
+use crate::health::{Armor, DeathEvent, Health};
+use crate::hitbox::Hitbox;
+use crate::inventory::WeaponInventory;
+use crate::player::{create_player, LookInput, Player, PlayerVelocity};
+
+/// Spawns used more recently than this are only picked when there's nothing else
+const RECENT_SPAWN_TIME: f32 = 10.0;
+
+/// Somewhere players can be spawned, placed in maps with the `player_spawn` entity
+#[derive(Component, Default)]
+pub struct PlayerSpawn {
+    /// Elapsed seconds when a player was last spawned here
+    last_used: Option<f32>,
+}
+
+/// Marks a player as dead until they respawn
+#[derive(Component)]
+pub struct Dead {
+    pub respawn: Timer,
+}
+
+/// Damage is ignored while this is on a player
+#[derive(Component)]
+pub struct SpawnProtection(pub Timer);
+
+pub trait MakeEntityPlayerSpawn<'w> {
+    fn make_player_spawn(&'w mut self, transform: Transform) -> &'w mut Self;
+}
+
+impl<'w> MakeEntityPlayerSpawn<'w> for EntityWorldMut<'w> {
+    fn make_player_spawn(&mut self, transform: Transform) -> &'w mut EntityWorldMut {
+        self.insert((PlayerSpawn::default(), SpatialBundle::from_transform(transform)))
+    }
+}
+
+/// Picks the spawn furthest from any other player, steering clear of spawns that were just used
+fn choose_spawn<'a>(
+    spawns: impl Iterator<Item = (Entity, &'a PlayerSpawn, &'a GlobalTransform)>,
+    enemy_positions: &[Vec3],
+    now: f32,
+) -> Option<(Entity, Transform)> {
+    spawns
+        .map(|(entity, spawn, transform)| {
+            let recently_used = spawn.last_used.is_some_and(|last_used| now - last_used < RECENT_SPAWN_TIME);
+            let nearest_enemy = enemy_positions.iter()
+                .map(|position| position.distance(transform.translation()))
+                .fold(f32::MAX, f32::min);
+            (entity, transform.compute_transform(), !recently_used, nearest_enemy)
+        })
+        .max_by(|a, b| (a.2, a.3).partial_cmp(&(b.2, b.3)).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(entity, transform, ..)| (entity, transform))
+}
+
+// Yaw from the spawn's rotation, so the player faces the way the spawn point does
+fn look_from_spawn(transform: &Transform) -> Vec2 {
+    let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+    Vec2::new(yaw.to_degrees(), 0.0)
+}
+
+fn spawn_initial_player(
+    players: Query<(), With<Player>>,
+    mut spawns: Query<(Entity, &mut PlayerSpawn, &GlobalTransform)>,
+    mut look_input: ResMut<LookInput>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    if !players.is_empty() {
+        return;
+    }
+
+    let now = time.elapsed_seconds();
+    let Some((spawn_entity, transform)) = choose_spawn(spawns.iter(), &[], now) else {
+        return;
+    };
+    if let Ok((_, mut spawn, _)) = spawns.get_mut(spawn_entity) {
+        spawn.last_used = Some(now);
+    }
+
+    **look_input = look_from_spawn(&transform);
+    create_player(&mut commands, transform);
+}
+
+fn handle_player_death(
+    trigger: Trigger<DeathEvent>,
+    players: Query<Option<&Children>, With<Player>>,
+    hitboxes: Query<(), With<Hitbox>>,
+    console: Res<DeveloperConsole>,
+    mut commands: Commands,
+) {
+    let Ok(children) = players.get(trigger.entity()) else {
+        return;
+    };
+    let respawn_time = console.get_value::<f32>("mp_respawn_time").unwrap_or(0.0);
+
+    // The body stays around for the camera, but can't be bumped into or shot any more
+    commands.entity(trigger.entity())
+        .insert(Dead { respawn: Timer::from_seconds(respawn_time, TimerMode::Once) })
+        .insert(ColliderDisabled);
+    if let Some(children) = children {
+        for &child in children.iter().filter(|&&child| hitboxes.contains(child)) {
+            commands.entity(child).insert(ColliderDisabled);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn respawn_dead_players(
+    mut dead_players: Query<(Entity, &mut Dead, &mut Transform, &mut Health, Option<&mut Armor>, &mut PlayerVelocity, Option<&Children>)>,
+    living_players: Query<&GlobalTransform, (With<Player>, Without<Dead>)>,
+    mut spawns: Query<(Entity, &mut PlayerSpawn, &GlobalTransform)>,
+    hitboxes: Query<(), With<Hitbox>>,
+    mut look_input: ResMut<LookInput>,
+    console: Res<DeveloperConsole>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let protection_time = console.get_value::<f32>("mp_spawn_protection").unwrap_or(0.0);
+    let now = time.elapsed_seconds();
+    let enemy_positions: Vec<Vec3> = living_players.iter().map(GlobalTransform::translation).collect();
+
+    for (player, mut dead, mut transform, mut health, armor, mut velocity, children) in dead_players.iter_mut() {
+        if !dead.respawn.tick(time.delta()).finished() {
+            continue;
+        }
+        let Some((spawn_entity, spawn_transform)) = choose_spawn(spawns.iter(), &enemy_positions, now) else {
+            error!("No player_spawn to respawn {player:?} at");
+            continue;
+        };
+        if let Ok((_, mut spawn, _)) = spawns.get_mut(spawn_entity) {
+            spawn.last_used = Some(now);
+        }
+
+        *transform = spawn_transform;
+        **look_input = look_from_spawn(&spawn_transform);
+        health.reset();
+        if let Some(mut armor) = armor {
+            armor.current = 0;
+        }
+        *velocity = PlayerVelocity::default();
+
+        // The old inventory went with the body, a fresh default loadout gets equipped
+        commands.entity(player)
+            .remove::<(Dead, ColliderDisabled, WeaponInventory)>()
+            .insert(SpawnProtection(Timer::from_seconds(protection_time, TimerMode::Once)));
+        if let Some(children) = children {
+            for &child in children.iter().filter(|&&child| hitboxes.contains(child)) {
+                commands.entity(child).remove::<ColliderDisabled>();
+            }
+        }
+    }
+}
+
+fn expire_spawn_protection(
+    mut protected: Query<(Entity, &mut SpawnProtection)>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, mut protection) in protected.iter_mut() {
+        if protection.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<SpawnProtection>();
+        }
+    }
+}
+
+pub fn respawn_console_plugin(app: &mut App) {
+    app.add_cvar("mp_respawn_time", 3.0f32)
+        .add_cvar("mp_spawn_protection", 2.0f32);
+}
+
+pub fn respawn_plugin(app: &mut App) {
+    app.add_plugins(respawn_console_plugin)
+        .add_systems(Update, (spawn_initial_player, respawn_dead_players, expire_spawn_protection))
+        .observe(handle_player_death);
+}
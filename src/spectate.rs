@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+use crate::dev_console::{AddConsoleVariable, DeveloperConsole};
+use crate::flycam::{flycam_plugin, FlyCam};
+use crate::health::KillEvent;
+use crate::input::{ActionInput, InputAction};
+use crate::player::{Player, PlayerCamera};
+use crate::respawn::Dead;
+
+#[derive(Default)]
+enum SpectatorMode {
+    #[default]
+    Off,
+    /// Looking at whoever did the killing from where we died
+    DeathCam { killer: Option<Entity>, timer: Timer },
+    /// Seeing through another living player's eyes
+    Following(Entity),
+    FreeCam,
+}
+
+/// What a dead player is watching until they respawn
+#[derive(Resource, Default)]
+pub struct Spectator {
+    mode: SpectatorMode,
+    /// The dead player doing the spectating
+    spectator: Option<Entity>,
+}
+
+// Living players other than the spectator, in a stable order to cycle through
+fn spectate_targets(
+    spectator: Option<Entity>,
+    living_players: &Query<Entity, (With<Player>, Without<Dead>)>,
+    player_cameras: &Query<(&GlobalTransform, &Parent), With<PlayerCamera>>,
+) -> Vec<Entity> {
+    // Same as for the death cam, there's nothing to see through a player without a camera of their own
+    let mut targets: Vec<Entity> = living_players.iter()
+        .filter(|&player| Some(player) != spectator)
+        .filter(|&player| player_cameras.iter().any(|(_, parent)| parent.get() == player))
+        .collect();
+    targets.sort();
+    targets
+}
+
+fn next_target(current: Option<Entity>, targets: &[Entity]) -> SpectatorMode {
+    let next = match current.and_then(|current| targets.iter().position(|&target| target == current)) {
+        Some(index) => targets.get(index + 1),
+        None => targets.first(),
+    };
+    // Run out of players to watch, so fly around instead
+    next.map_or(SpectatorMode::FreeCam, |&target| SpectatorMode::Following(target))
+}
+
+fn start_death_cam(
+    mut kills: EventReader<KillEvent>,
+    cameras: Query<(), With<PlayerCamera>>,
+    player_children: Query<&Children, With<Player>>,
+    mut spectator: ResMut<Spectator>,
+    console: Res<DeveloperConsole>,
+) {
+    for kill in kills.read() {
+        // Only players with a camera of their own get to spectate
+        let has_camera = player_children.get(kill.victim)
+            .is_ok_and(|children| children.iter().any(|&child| cameras.contains(child)));
+        if !has_camera {
+            continue;
+        }
+
+        let death_cam_time = console.get_value::<f32>("mp_deathcam_time").unwrap_or(0.0);
+        *spectator = Spectator {
+            mode: SpectatorMode::DeathCam {
+                killer: kill.killer,
+                timer: Timer::from_seconds(death_cam_time, TimerMode::Once),
+            },
+            spectator: Some(kill.victim),
+        };
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_spectator(
+    mut spectator: ResMut<Spectator>,
+    living_players: Query<Entity, (With<Player>, Without<Dead>)>,
+    dead_players: Query<(), (With<Player>, With<Dead>)>,
+    transforms: Query<&GlobalTransform>,
+    player_cameras: Query<(&GlobalTransform, &Parent), With<PlayerCamera>>,
+    mut camera_transforms: Query<(&mut Transform, &Parent), (With<PlayerCamera>, Without<FlyCam>)>,
+    mut fly_cams: Query<&mut Transform, With<FlyCam>>,
+    actions: ActionInput,
+    time: Res<Time>,
+) {
+    let Some(spectating_player) = spectator.spectator else {
+        return;
+    };
+    // Back among the living, so back to our own eyes
+    if !dead_players.contains(spectating_player) {
+        *spectator = Spectator::default();
+        return;
+    }
+
+    let targets = spectate_targets(Some(spectating_player), &living_players, &player_cameras);
+    let own_camera = player_cameras.iter().find(|(_, parent)| parent.get() == spectating_player);
+
+    let next_mode = match &mut spectator.mode {
+        SpectatorMode::Off => None,
+        SpectatorMode::DeathCam { killer, timer } => {
+            // Turn to face the killer, unless it was suicide or the map that did it
+            let killer_position = killer.and_then(|killer| transforms.get(killer).ok());
+            if let (Some(killer_position), Some((camera_transform, _))) = (killer_position, own_camera) {
+                let direction = (killer_position.translation() - camera_transform.translation()).normalize_or_zero();
+                // The dead don't look around themselves, so the camera is turned relative to the body it's on
+                let body_rotation = transforms.get(spectating_player).map_or(Quat::IDENTITY, |body| body.compute_transform().rotation);
+                let own_camera_transform = camera_transforms.iter_mut().find(|(_, parent)| parent.get() == spectating_player);
+                if let Some((mut transform, _)) = own_camera_transform.filter(|_| direction != Vec3::ZERO) {
+                    transform.rotation = body_rotation.inverse() * Transform::default().looking_to(direction, Vec3::Y).rotation;
+                }
+            }
+
+            timer.tick(time.delta()).finished().then(|| next_target(None, &targets))
+        }
+        SpectatorMode::Following(target) => {
+            if !targets.contains(target) || actions.just_pressed(InputAction::Fire) {
+                Some(next_target(Some(*target), &targets))
+            } else if actions.just_pressed(InputAction::Jump) {
+                Some(SpectatorMode::FreeCam)
+            } else {
+                None
+            }
+        }
+        SpectatorMode::FreeCam => {
+            actions.just_pressed(InputAction::Fire).then(|| next_target(None, &targets))
+                .filter(|mode| !matches!(mode, SpectatorMode::FreeCam))
+        }
+    };
+
+    if let Some(mode) = next_mode {
+        // The free camera starts off from wherever we were looking from
+        if let (SpectatorMode::FreeCam, Some((camera_transform, _))) = (&mode, own_camera) {
+            for mut fly_cam_transform in fly_cams.iter_mut() {
+                *fly_cam_transform = camera_transform.compute_transform();
+            }
+        }
+        spectator.mode = mode;
+    }
+}
+
+// Only one camera renders at a time, whichever one the spectator says we're looking through
+fn apply_spectator_camera(
+    spectator: Res<Spectator>,
+    mut player_cameras: Query<(&mut Camera, &Parent), (With<PlayerCamera>, Without<FlyCam>)>,
+    mut fly_cams: Query<&mut Camera, With<FlyCam>>,
+) {
+    if !spectator.is_changed() {
+        return;
+    }
+
+    let watched_player = match spectator.mode {
+        SpectatorMode::Off | SpectatorMode::DeathCam { .. } => spectator.spectator,
+        SpectatorMode::Following(target) => Some(target),
+        SpectatorMode::FreeCam => None,
+    };
+
+    for (mut camera, parent) in player_cameras.iter_mut() {
+        // With nobody spectating, every player's camera goes back to how it was
+        camera.is_active = match (&spectator.mode, watched_player) {
+            (SpectatorMode::Off, _) => true,
+            (_, Some(watched_player)) => parent.get() == watched_player,
+            (_, None) => false,
+        };
+    }
+    for mut camera in fly_cams.iter_mut() {
+        camera.is_active = matches!(spectator.mode, SpectatorMode::FreeCam);
+    }
+}
+
+pub fn spectate_console_plugin(app: &mut App) {
+    app.add_cvar("mp_deathcam_time", 2.0f32);
+}
+
+pub fn spectate_plugin(app: &mut App) {
+    app.add_plugins(flycam_plugin)
+        .add_plugins(spectate_console_plugin)
+        .init_resource::<Spectator>()
+        .add_systems(Update, (start_death_cam, update_spectator, apply_spectator_camera).chain());
+}
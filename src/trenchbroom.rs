@@ -4,7 +4,11 @@ use bevy_trenchbroom::config::TrenchBroomConfig;
 use bevy_trenchbroom::entity_definitions;
 use bevy::prelude::*;
 use bevy::log::error;
-use crate::player::MakeEntityPlayer;
+use crate::breakable::{BreakExplosion, MakeEntityBreakable};
+use crate::hazard::MakeEntityTriggerHurt;
+use crate::inventory::{weapon_path, MakeEntityWeaponPickup};
+use crate::pickup::{MakeEntityArmorPickup, MakeEntityHealthPickup};
+use crate::respawn::MakeEntityPlayerSpawn;
 
 #[cfg(target_os = "windows")]
 const PATH_SEPARATOR: char = '\\';
@@ -12,6 +16,24 @@ const PATH_SEPARATOR: char = '\\';
 #[cfg(not(target_os = "windows"))]
 const PATH_SEPARATOR: char = '/';
 
+const DEFAULT_PICKUP_RESPAWN_TIME: f32 = 20.0;
+const DEFAULT_ARMOR_PICKUP_AMOUNT: i32 = 50;
+const DEFAULT_HEALTH_PICKUP_AMOUNT: i32 = 25;
+const DEFAULT_TRIGGER_HURT_INTERVAL: f32 = 0.5;
+const DEFAULT_BREAKABLE_HEALTH: i32 = 50;
+const DEFAULT_BREAKABLE_DEBRIS: u32 = 8;
+const DEFAULT_EXPLOSION_RADIUS: f32 = 4.0;
+const DEFAULT_EXPLOSION_KNOCKBACK: f32 = 10.0;
+
+// Only explodes when the mapper gave it some damage to do
+fn breakable_explosion(damage: Option<i32>, radius: Option<f32>, knockback: Option<f32>) -> Option<BreakExplosion> {
+    damage.filter(|&damage| damage > 0).map(|damage| BreakExplosion {
+        damage,
+        radius: radius.unwrap_or(DEFAULT_EXPLOSION_RADIUS),
+        knockback: knockback.unwrap_or(DEFAULT_EXPLOSION_KNOCKBACK),
+    })
+}
+
 pub fn trenchbroom_config() -> TrenchBroomConfig {
     TrenchBroomConfig::new("lan-shootmans")
         .entity_scale_expression("scale")
@@ -25,6 +47,44 @@ pub fn trenchbroom_config() -> TrenchBroomConfig {
                 // Here, we also call smooth_by_default_angle(), which smooths the normals of connected surfaces curving less than a default threshold
             }
 
+            /// Hurts players inside it every interval, for lava, pits and kill volumes
+            Solid trigger_hurt {
+                /// Damage dealt every interval, something huge makes it a kill volume
+                damage: i32,
+                /// Seconds between each time it hurts
+                interval: f32,
+                /// One of environment, fall, explosive, bullet or melee
+                damage_type: String,
+            } |world, entity, view| {
+                // Only a collider, nothing to see
+                view.spawn_brushes(world, entity, BrushSpawnSettings::new().convex_collider());
+                let damage = view.get("damage")?;
+                let interval = view.get("interval").unwrap_or(DEFAULT_TRIGGER_HURT_INTERVAL);
+                let damage_type = view.get("damage_type").unwrap_or_else(|_| "environment".to_string());
+                world.entity_mut(entity)
+                    .make_trigger_hurt(damage, interval, &damage_type);
+            }
+
+            /// Brushes that shatter into debris once they've taken enough damage
+            Solid func_breakable {
+                health: i32,
+                /// Chunks of debris left flying when it breaks
+                debris: u32,
+                /// Blast damage when it breaks, leave empty for it not to explode
+                explode_damage: i32,
+                explode_radius: f32,
+                explode_knockback: f32,
+                /// Weapon left behind once it's broken, as in assets/weapons/<weapon>.weapon.ron
+                drop_weapon: String,
+            } |world, entity, view| {
+                view.spawn_brushes(world, entity, BrushSpawnSettings::new().smooth_by_default_angle().pbr_mesh().convex_collider());
+                let health = view.get("health").unwrap_or(DEFAULT_BREAKABLE_HEALTH);
+                let debris = view.get("debris").unwrap_or(DEFAULT_BREAKABLE_DEBRIS);
+                let explosion = breakable_explosion(view.get("explode_damage").ok(), view.get("explode_radius").ok(), view.get("explode_knockback").ok());
+                world.entity_mut(entity)
+                    .make_breakable(health, debris, explosion, view.get("drop_weapon").ok());
+            }
+
             // Some useful base classes
             Base angles {
                 /// Pitch Yaw Roll (Y Z X)
@@ -59,7 +119,66 @@ pub fn trenchbroom_config() -> TrenchBroomConfig {
 
             Point player_spawn(size(-20 -20 -46, 20 20 34) color(0 255 0)) {} |world, entity, view| {
                 world.entity_mut(entity)
-                    .make_player(view.get_transform());
+                    .make_player_spawn(view.get_transform());
+            }
+
+            Point weapon_pickup(size(-16 -16 -8, 16 16 8) color(255 200 0)) {
+                /// Weapon definition name, as in assets/weapons/<weapon>.weapon.ron
+                weapon: String,
+                /// Seconds until the weapon comes back after being picked up
+                respawn_time: f32,
+            } |world, entity, view| {
+                let weapon: String = view.get("weapon")?;
+                let respawn_time = view.get("respawn_time").unwrap_or(DEFAULT_PICKUP_RESPAWN_TIME);
+                let definition = world.resource::<AssetServer>().load(weapon_path(&weapon));
+                world.entity_mut(entity)
+                    .make_weapon_pickup(view.get_transform(), definition, Some(respawn_time), None);
+            }
+
+            Point armor_pickup(size(-16 -16 -8, 16 16 8) color(0 100 255)) {
+                /// Armor given to the player who picks it up
+                amount: i32,
+                /// Seconds until the armor comes back after being picked up
+                respawn_time: f32,
+            } |world, entity, view| {
+                let amount = view.get("amount").unwrap_or(DEFAULT_ARMOR_PICKUP_AMOUNT);
+                let respawn_time = view.get("respawn_time").unwrap_or(DEFAULT_PICKUP_RESPAWN_TIME);
+                world.entity_mut(entity)
+                    .make_armor_pickup(view.get_transform(), amount, respawn_time);
+            }
+
+            Point health_pickup(size(-12 -12 -12, 12 12 12) color(0 255 100)) {
+                /// Health given to the player who picks it up
+                amount: i32,
+                /// Whether it can heal above max health
+                overheal: bool,
+                /// Seconds until the health comes back after being picked up
+                respawn_time: f32,
+            } |world, entity, view| {
+                let amount = view.get("amount").unwrap_or(DEFAULT_HEALTH_PICKUP_AMOUNT);
+                let overheal = view.get("overheal").unwrap_or(false);
+                let respawn_time = view.get("respawn_time").unwrap_or(DEFAULT_PICKUP_RESPAWN_TIME);
+                world.entity_mut(entity)
+                    .make_health_pickup(view.get_transform(), amount, overheal, respawn_time);
+            }
+
+            /// A crate that breaks like a func_breakable, for when there's no need to build one out of brushes
+            Point prop_breakable(size(-8 -8 -8, 8 8 8) color(160 100 50)) {
+                health: i32,
+                /// Chunks of debris left flying when it breaks
+                debris: u32,
+                /// Blast damage when it breaks, leave empty for it not to explode
+                explode_damage: i32,
+                explode_radius: f32,
+                explode_knockback: f32,
+                /// Weapon left behind once it's broken, as in assets/weapons/<weapon>.weapon.ron
+                drop_weapon: String,
+            } |world, entity, view| {
+                let health = view.get("health").unwrap_or(DEFAULT_BREAKABLE_HEALTH);
+                let debris = view.get("debris").unwrap_or(DEFAULT_BREAKABLE_DEBRIS);
+                let explosion = breakable_explosion(view.get("explode_damage").ok(), view.get("explode_radius").ok(), view.get("explode_knockback").ok());
+                world.entity_mut(entity)
+                    .make_breakable_prop(view.get_transform(), health, debris, explosion, view.get("drop_weapon").ok());
             }
         })
 }
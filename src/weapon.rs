@@ -0,0 +1,676 @@
+use std::f32::consts::TAU;
+use std::time::Duration;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext, LoadedFolder};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rapier3d::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use crate::health::{DamageSource, DamageType, PotentialDamageEvent};
+use crate::hitbox::{HitTargets, HitboxMultipliers};
+use crate::impact::{impact_plugin, ImpactEvent, ImpactKind, ReplicatedImpact};
+use crate::input::{ActionInput, InputAction};
+use crate::inventory::{handle_weapon_switching, inventory_plugin, weapon_name, WeaponInventory};
+use crate::melee::{melee_plugin, MeleeDefinition};
+use crate::netcode::{ClientMessage, LocalClientId, ServerMessage};
+use crate::player::{LookInput, Player, PlayerCamera};
+use crate::respawn::Dead;
+use crate::projectile::{projectile_plugin, spawn_projectile, ProjectileAssets, ProjectileDefinition};
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum FireMode {
+    Semi,
+    Auto,
+    /// Fires `count` shots at the weapon's fire rate for a single trigger pull
+    Burst { count: u32 },
+}
+
+/// Damage scales linearly from full at `start` down to `min_multiplier` at `end`
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct DamageFalloff {
+    pub start: f32,
+    pub end: f32,
+    pub min_multiplier: f32,
+}
+
+impl Default for DamageFalloff {
+    fn default() -> Self {
+        Self {
+            start: f32::MAX,
+            end: f32::MAX,
+            min_multiplier: 1.0,
+        }
+    }
+}
+
+impl DamageFalloff {
+    pub fn multiplier(&self, distance: f32) -> f32 {
+        if distance <= self.start {
+            return 1.0;
+        }
+        if distance >= self.end {
+            return self.min_multiplier;
+        }
+
+        let t = (distance - self.start) / (self.end - self.start);
+        1.0 + (self.min_multiplier - 1.0) * t
+    }
+}
+
+/// How much less accurate the weapon gets from sustained fire and moving around, all in degrees
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+pub struct AccuracyDefinition {
+    /// Added to the spread with every shot
+    pub per_shot: f32,
+    /// Taken off the added spread every second
+    pub recovery: f32,
+    /// Cap on the total spread, 0 leaves it uncapped
+    pub max: f32,
+    pub moving: f32,
+    pub airborne: f32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+pub struct RecoilKick {
+    /// Degrees, positive kicks the view up
+    pub pitch: f32,
+    /// Degrees, positive kicks the view left
+    pub yaw: f32,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct RecoilDefinition {
+    /// Kick for each consecutive shot, the last one repeating once the pattern runs out
+    pub pattern: Vec<RecoilKick>,
+    /// Degrees a second the view drifts back towards where it was aimed before the kicks
+    pub recovery: f32,
+}
+
+impl RecoilDefinition {
+    fn kick(&self, consecutive_shot: u32) -> Vec2 {
+        self.pattern.get(consecutive_shot as usize)
+            .or(self.pattern.last())
+            .map_or(Vec2::ZERO, |kick| Vec2::new(kick.yaw, kick.pitch))
+    }
+}
+
+/// How much a hitscan round can punch through before it stops
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+pub struct PenetrationDefinition {
+    /// Total thickness, in units, of everything the round can pass through
+    pub depth: f32,
+    /// Fraction of the damage lost for every unit of thickness passed through
+    pub damage_loss: f32,
+}
+
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct WeaponDefinition {
+    pub name: String,
+    /// Inventory slot the weapon goes in, matching the number key that selects it
+    pub slot: usize,
+    /// Seconds to pull the weapon out before it can be fired
+    #[serde(default = "default_draw_time")]
+    pub draw_time: f32,
+    /// Seconds to put the weapon away before switching to another
+    #[serde(default = "default_holster_time")]
+    pub holster_time: f32,
+    /// Damage per pellet, before falloff
+    pub damage: i32,
+    /// Shots per second
+    pub fire_rate: f32,
+    pub range: f32,
+    /// Half angle of the spread cone when standing still and firing the first shot, in degrees
+    #[serde(default)]
+    pub spread: f32,
+    #[serde(default)]
+    pub accuracy: AccuracyDefinition,
+    #[serde(default)]
+    pub recoil: RecoilDefinition,
+    #[serde(default = "default_pellets")]
+    pub pellets: u32,
+    #[serde(default)]
+    pub falloff: DamageFalloff,
+    #[serde(default)]
+    pub hitbox_multipliers: HitboxMultipliers,
+    /// Quick melee done while holding the weapon
+    #[serde(default)]
+    pub melee: MeleeDefinition,
+    pub fire_mode: FireMode,
+    pub magazine_size: u32,
+    /// Reserve ammo the weapon is picked up with
+    pub reserve_ammo: u32,
+    /// Seconds, firing with rounds still in the magazine interrupts a reload
+    pub reload_time: f32,
+    #[serde(default)]
+    pub penetration: PenetrationDefinition,
+    /// Fires physically simulated projectiles instead of instant rays when set
+    #[serde(default)]
+    pub projectile: Option<ProjectileDefinition>,
+}
+
+fn default_pellets() -> u32 {
+    1
+}
+
+fn default_draw_time() -> f32 {
+    0.4
+}
+
+fn default_holster_time() -> f32 {
+    0.2
+}
+
+impl WeaponDefinition {
+    pub fn shot_interval(&self) -> f32 {
+        1.0 / self.fire_rate
+    }
+
+    pub fn current_spread(&self, bloom: f32, moving: bool, airborne: bool) -> f32 {
+        let mut spread = self.spread + bloom;
+        if moving {
+            spread += self.accuracy.moving;
+        }
+        if airborne {
+            spread += self.accuracy.airborne;
+        }
+
+        if self.accuracy.max > 0.0 {
+            spread.min(self.accuracy.max)
+        } else {
+            spread
+        }
+    }
+}
+
+#[derive(Default)]
+struct WeaponDefinitionLoader;
+
+impl AssetLoader for WeaponDefinitionLoader {
+    type Asset = WeaponDefinition;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes::<WeaponDefinition>(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["weapon.ron"]
+    }
+}
+
+pub struct Weapon {
+    pub definition: Handle<WeaponDefinition>,
+    magazine: u32,
+    reserve: u32,
+    reload: Option<Timer>,
+    cooldown: f32,
+    burst_remaining: u32,
+    /// Extra spread built up from sustained fire
+    bloom: f32,
+    /// Recoil kicked into the view that hasn't been recovered yet
+    recoil: Vec2,
+    /// Shots since the weapon last settled, indexing into the recoil pattern
+    consecutive_shots: u32,
+    since_last_shot: f32,
+    /// Every shot the weapon has fired, seeding its spread
+    shots_fired: u32,
+}
+
+impl Weapon {
+    pub fn new(definition_handle: Handle<WeaponDefinition>, definition: &WeaponDefinition) -> Self {
+        Self {
+            definition: definition_handle,
+            magazine: definition.magazine_size,
+            reserve: definition.reserve_ammo,
+            reload: None,
+            cooldown: 0.0,
+            burst_remaining: 0,
+            bloom: 0.0,
+            recoil: Vec2::ZERO,
+            consecutive_shots: 0,
+            since_last_shot: 0.0,
+            shots_fired: 0,
+        }
+    }
+
+    /// A weapon that has already seen some use, like one dropped by a dead player
+    pub fn with_ammo(definition_handle: Handle<WeaponDefinition>, magazine: u32, reserve: u32) -> Self {
+        Self {
+            definition: definition_handle,
+            magazine,
+            reserve,
+            reload: None,
+            cooldown: 0.0,
+            burst_remaining: 0,
+            bloom: 0.0,
+            recoil: Vec2::ZERO,
+            consecutive_shots: 0,
+            since_last_shot: 0.0,
+            shots_fired: 0,
+        }
+    }
+
+    pub fn magazine(&self) -> u32 {
+        self.magazine
+    }
+
+    pub fn reserve(&self) -> u32 {
+        self.reserve
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.reload.is_some()
+    }
+
+    /// Returns whether the weapon is reloading afterwards
+    pub fn start_reload(&mut self, definition: &WeaponDefinition) -> bool {
+        if self.reload.is_none() && self.magazine < definition.magazine_size && self.reserve > 0 {
+            self.reload = Some(Timer::from_seconds(definition.reload_time, TimerMode::Once));
+            self.burst_remaining = 0;
+        }
+        self.is_reloading()
+    }
+
+    pub fn cancel_reload(&mut self) {
+        self.reload = None;
+    }
+
+    pub fn add_reserve(&mut self, amount: u32) {
+        self.reserve += amount;
+    }
+
+    fn tick(&mut self, delta: Duration, definition: &WeaponDefinition) {
+        self.cooldown = (self.cooldown - delta.as_secs_f32()).max(0.0);
+
+        self.bloom = (self.bloom - definition.accuracy.recovery * delta.as_secs_f32()).max(0.0);
+        self.since_last_shot += delta.as_secs_f32();
+        // A pause of a couple of shots' worth counts as letting go of the trigger
+        if self.bloom <= 0.0 && self.since_last_shot > definition.shot_interval() * 2.0 {
+            self.consecutive_shots = 0;
+        }
+
+        let Some(reload) = self.reload.as_mut() else {
+            return;
+        };
+        if reload.tick(delta).finished() {
+            let loaded = (definition.magazine_size - self.magazine).min(self.reserve);
+            self.magazine += loaded;
+            self.reserve -= loaded;
+            self.reload = None;
+        }
+    }
+}
+
+/// Reload state of other players, as relayed by the server
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct RemoteReloadStates(HashMap<u64, bool>);
+
+fn send_reload_state(
+    player_query: Query<&WeaponInventory, With<Player>>,
+    mut was_reloading: Local<bool>,
+    mut messages: EventWriter<ClientMessage>,
+) {
+    let reloading = player_query.get_single().ok()
+        .and_then(WeaponInventory::active_weapon)
+        .is_some_and(Weapon::is_reloading);
+    if reloading != *was_reloading {
+        messages.send(ClientMessage::ReloadStateChanged { reloading });
+        *was_reloading = reloading;
+    }
+}
+
+fn receive_reload_states(
+    mut messages: EventReader<ServerMessage>,
+    mut remote_reload_states: ResMut<RemoteReloadStates>,
+) {
+    for message in messages.read() {
+        match message {
+            ServerMessage::ReloadStateChanged { client_id, reloading } => {
+                remote_reload_states.insert(*client_id, *reloading);
+            }
+            ServerMessage::ClientDisconnected { client_id } => {
+                remote_reload_states.remove(client_id);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Resource)]
+struct WeaponSounds {
+    dry_fire: Handle<AudioSource>,
+}
+
+fn setup_weapon_sounds(asset_server: Res<AssetServer>, mut commands: Commands) {
+    commands.insert_resource(WeaponSounds {
+        dry_fire: asset_server.load("sounds/dry_fire.wav"),
+    });
+}
+
+fn wants_to_fire(fire_mode: FireMode, actions: &ActionInput) -> bool {
+    match fire_mode {
+        FireMode::Semi | FireMode::Burst { .. } => actions.just_pressed(InputAction::Fire),
+        FireMode::Auto => actions.pressed(InputAction::Fire),
+    }
+}
+
+const PENETRATION_EXIT_OFFSET: f32 = 0.01;
+
+/// Thickness of `entity` along the ray from where it was hit, if the round makes it out the other side within `max_depth`
+fn measure_thickness(
+    rapier_context: &RapierContext,
+    entity: Entity,
+    entry_point: Vec3,
+    direction: Vec3,
+    max_depth: f32,
+) -> Option<f32> {
+    if max_depth <= 0.0 {
+        return None;
+    }
+
+    // Cast back from as deep as the round could possibly go, whatever it hits first is where it would come out
+    let only_entity = |other: Entity| other == entity;
+    let (_, toi) = rapier_context.cast_ray(
+        entry_point + direction * max_depth,
+        -direction,
+        max_depth,
+        true,
+        QueryFilter::default().predicate(&only_entity),
+    )?;
+
+    // Starting inside a solid collider means it's thicker than what's left to get through. The map is a trimesh
+    // with no inside, so there the cast goes all the way back to where the round went in instead
+    let thickness = max_depth - toi;
+    (toi > 0.0 && toi < max_depth - PENETRATION_EXIT_OFFSET).then_some(thickness)
+}
+
+/// Spread is seeded by who fired and the shot number, so anyone who knows which shot it was agrees on where every pellet went
+pub fn shot_rng(client_id: u64, shot: u32) -> StdRng {
+    StdRng::seed_from_u64(client_id.rotate_left(32) ^ shot as u64)
+}
+
+fn spread_direction(camera_rotation: Quat, spread: f32, rng: &mut impl Rng) -> Vec3 {
+    if spread <= 0.0 {
+        return camera_rotation * Vec3::NEG_Z;
+    }
+
+    let deviation = rng.gen_range(0.0..spread.to_radians());
+    let roll = rng.gen_range(0.0..TAU);
+    camera_rotation * Quat::from_rotation_z(roll) * Quat::from_rotation_x(deviation) * Vec3::NEG_Z
+}
+
+/// How far an impact may be from where its pellet could have gone before the shot is taken as made up
+const SHOT_TOLERANCE: f32 = 0.1;
+
+/// A hitscan shot as it's sent over the network, with everything needed to work out its spread again
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplicatedShot {
+    /// Name of the weapon fired, as in assets/weapons/<weapon>.weapon.ron
+    pub weapon: String,
+    pub shot: u32,
+    origin: [f32; 3],
+    rotation: [f32; 4],
+    /// Only trusted as far as the weapon's own spread allows
+    spread: f32,
+    pub impacts: Vec<ReplicatedImpact>,
+}
+
+impl ReplicatedShot {
+    pub fn pellet_directions(&self, client_id: u64, definition: &WeaponDefinition) -> Vec<Vec3> {
+        let mut rng = shot_rng(client_id, self.shot);
+        let rotation = Quat::from_array(self.rotation);
+        (0..definition.pellets).map(|_| spread_direction(rotation, self.spread, &mut rng)).collect()
+    }
+
+    /// Whether the spread is one the weapon could have had, and every impact lies along one of the pellets
+    /// the shot's seed says were fired
+    pub fn impacts_match(&self, client_id: u64, definition: &WeaponDefinition) -> bool {
+        // Bloom and moving around only ever add to the spread, up to the cap if there is one
+        let max_spread = if definition.accuracy.max > 0.0 { definition.accuracy.max } else { f32::INFINITY };
+        if self.spread < definition.spread.min(max_spread) || self.spread > max_spread {
+            return false;
+        }
+
+        let origin = Vec3::from_array(self.origin);
+        let directions = self.pellet_directions(client_id, definition);
+        self.impacts.iter().all(|impact| {
+            let offset = ImpactEvent::from(impact).position - origin;
+            offset.length() <= definition.range + SHOT_TOLERANCE && directions.iter().any(|direction| {
+                offset.dot(*direction) >= 0.0 && offset.reject_from_normalized(*direction).length() <= SHOT_TOLERANCE
+            })
+        })
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fire_weapons(
+    camera_query: Query<&GlobalTransform, With<PlayerCamera>>,
+    mut player_query: Query<(Entity, &mut WeaponInventory, Option<&KinematicCharacterControllerOutput>), (With<Player>, Without<Dead>)>,
+    targets: HitTargets,
+    mut look_input: ResMut<LookInput>,
+    weapon_definitions: Res<Assets<WeaponDefinition>>,
+    rapier_context: Res<RapierContext>,
+    projectile_assets: Res<ProjectileAssets>,
+    sounds: Res<WeaponSounds>,
+    actions: ActionInput,
+    time: Res<Time>,
+    mut impact_events: EventWriter<ImpactEvent>,
+    mut messages: EventWriter<ClientMessage>,
+    local_client: Option<Res<LocalClientId>>,
+    mut commands: Commands,
+) {
+    let Ok(player_camera_transform) = camera_query.get_single() else {
+        error!("could not find camera");
+        return;
+    };
+
+    let Ok((player, mut inventory, controller_output)) = player_query.get_single_mut() else {
+        return;
+    };
+
+    // Nothing can be fired while weapons are being swapped around
+    if inventory.is_switching() {
+        return;
+    }
+    let Some(weapon) = inventory.active_weapon_mut() else {
+        return;
+    };
+
+    let Some(definition) = weapon_definitions.get(&weapon.definition) else {
+        return;
+    };
+    let weapon_id = weapon.definition.id();
+    let replicated_weapon = weapon_name(&weapon.definition);
+
+    weapon.tick(time.delta(), definition);
+
+    let recovered = weapon.recoil.clamp_length_max(definition.recoil.recovery * time.delta_seconds());
+    weapon.recoil -= recovered;
+    **look_input -= recovered;
+
+    if actions.just_pressed(InputAction::Reload) {
+        weapon.start_reload(definition);
+    }
+
+    if weapon.cooldown > 0.0 {
+        return;
+    }
+
+    if weapon.burst_remaining == 0 && wants_to_fire(definition.fire_mode, &actions) {
+        if weapon.magazine == 0 {
+            // Pulling the trigger on an empty magazine reloads if there's anything to reload with
+            if !weapon.start_reload(definition) {
+                commands.spawn(AudioBundle {
+                    source: sounds.dry_fire.clone(),
+                    settings: PlaybackSettings::DESPAWN,
+                });
+                weapon.cooldown = definition.shot_interval();
+            }
+            return;
+        }
+
+        weapon.cancel_reload();
+        weapon.burst_remaining = match definition.fire_mode {
+            FireMode::Burst { count } => count,
+            _ => 1,
+        };
+    }
+    if weapon.burst_remaining == 0 {
+        return;
+    }
+    if weapon.magazine == 0 {
+        weapon.burst_remaining = 0;
+        return;
+    }
+    weapon.magazine -= 1;
+    weapon.burst_remaining -= 1;
+    weapon.cooldown = definition.shot_interval();
+
+    let moving = controller_output.is_some_and(|output| output.effective_translation.xz().length() > 0.01);
+    let airborne = controller_output.is_some_and(|output| !output.grounded);
+    let spread = definition.current_spread(weapon.bloom, moving, airborne);
+    let shot = weapon.shots_fired;
+    let mut rng = shot_rng(local_client.map_or(0, |client_id| **client_id), shot);
+
+    let kick = definition.recoil.kick(weapon.consecutive_shots);
+    weapon.recoil += kick;
+    **look_input += kick;
+    look_input.y = look_input.y.clamp(-89.9, 89.9);
+    weapon.bloom += definition.accuracy.per_shot;
+    weapon.consecutive_shots += 1;
+    weapon.since_last_shot = 0.0;
+    weapon.shots_fired = weapon.shots_fired.wrapping_add(1);
+
+    let ray_pos = player_camera_transform.translation();
+    let camera_rotation = player_camera_transform.compute_transform().rotation;
+
+    if let Some(projectile) = &definition.projectile {
+        for _ in 0..definition.pellets {
+            let direction = spread_direction(camera_rotation, spread, &mut rng);
+            // Spawned a little ahead of the camera so it doesn't start inside the player
+            let position = ray_pos + direction * (projectile.radius + 0.5);
+            spawn_projectile(&mut commands, &projectile_assets, projectile, player, DamageSource::Weapon(weapon_id), definition.damage, position, direction);
+        }
+        return;
+    }
+
+    let mut impacts = Vec::new();
+    for _ in 0..definition.pellets {
+        let ray_direction = spread_direction(camera_rotation, spread, &mut rng);
+        let mut origin = ray_pos;
+        let mut travelled = 0.0;
+        let mut damage_multiplier = 1.0;
+        let mut penetration_left = definition.penetration.depth;
+        // Players already passed through, so a round can't hit the same one twice
+        let mut passed_through: Vec<Entity> = Vec::new();
+
+        loop {
+            let hittable = |entity: Entity| targets.is_hittable(entity, player, &passed_through);
+            let query_filter = QueryFilter::default()
+                .exclude_collider(player)
+                .predicate(&hittable);
+
+            let Some((entity, intersection)) = rapier_context.cast_ray_and_get_normal(
+                origin,
+                ray_direction,
+                definition.range - travelled,
+                true,
+                query_filter,
+            ) else {
+                break;
+            };
+            travelled += intersection.time_of_impact;
+            let hit_point = intersection.point;
+            debug!("Hit entity '{:?}' at pos {}", entity, hit_point);
+
+            let hitbox = targets.hitbox(entity);
+            let victim = hitbox.map_or(entity, |hitbox| hitbox.owner);
+            // Hitbox region multipliers are left to the damage modifiers
+            let damage = definition.damage as f32 * definition.falloff.multiplier(travelled) * damage_multiplier;
+            commands.trigger_targets(PotentialDamageEvent {
+                damage: damage.round() as i32,
+                originating_entity: player,
+                damage_type: DamageType::Bullet,
+                source: DamageSource::Weapon(weapon_id),
+                hit_region: hitbox.map(|hitbox| hitbox.region),
+                origin: Some(ray_pos),
+            }, victim);
+
+            let impact = ImpactEvent {
+                position: hit_point,
+                normal: intersection.normal,
+                kind: if hitbox.is_some() { ImpactKind::Player } else { ImpactKind::World },
+                entity: Some(entity),
+            };
+            impacts.push(ReplicatedImpact::from(&impact));
+            impact_events.send(impact);
+
+            let Some(thickness) = measure_thickness(&rapier_context, entity, hit_point, ray_direction, penetration_left) else {
+                break;
+            };
+            penetration_left -= thickness;
+            damage_multiplier *= (1.0 - thickness * definition.penetration.damage_loss).max(0.0);
+            if damage_multiplier <= 0.0 {
+                break;
+            }
+
+            if hitbox.is_some() {
+                passed_through.push(victim);
+            }
+            // Nudged past the exit so the next cast doesn't just hit the far side of the same surface
+            travelled += thickness + PENETRATION_EXIT_OFFSET;
+            origin = hit_point + ray_direction * (thickness + PENETRATION_EXIT_OFFSET);
+        }
+    }
+
+    // Weapons that weren't loaded from the weapons folder have no name for the server to check the shot against
+    if let Some(weapon) = replicated_weapon.filter(|_| !impacts.is_empty()) {
+        messages.send(ClientMessage::Shot {
+            shot: ReplicatedShot {
+                weapon,
+                shot,
+                origin: ray_pos.to_array(),
+                rotation: camera_rotation.to_array(),
+                spread,
+                impacts,
+            },
+        });
+    }
+}
+
+// Every weapon definition is loaded up front, so pickups, the give command and checking shots never wait on one
+#[derive(Resource)]
+struct WeaponLibrary(#[allow(dead_code)] Handle<LoadedFolder>);
+
+fn load_weapon_library(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(WeaponLibrary(asset_server.load_folder("weapons")));
+}
+
+/// Just the definitions, so the server can check shots against them without running any of the weapons
+pub fn weapon_definitions_plugin(app: &mut App) {
+    app.init_asset::<WeaponDefinition>()
+        .init_asset_loader::<WeaponDefinitionLoader>()
+        .add_systems(Startup, load_weapon_library);
+}
+
+pub fn weapon_plugin(app: &mut App) {
+    app.add_plugins(projectile_plugin)
+        .add_plugins(inventory_plugin)
+        .add_plugins(melee_plugin)
+        .add_plugins(impact_plugin)
+        .add_plugins(weapon_definitions_plugin)
+        .insert_resource(RemoteReloadStates::default())
+        .add_systems(Startup, setup_weapon_sounds)
+        .add_systems(Update, (fire_weapons, send_reload_state).chain().after(handle_weapon_switching))
+        .add_systems(Update, receive_reload_states);
+}